@@ -1,3 +1,64 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
 fn main() {
+    check_translations(Path::new("i18n"));
     tauri_build::build()
 }
+
+/// Fail the build when a message exists in one locale but not in another,
+/// instead of silently showing the fallback text at runtime.
+fn check_translations(dir: &Path) {
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut locales: Vec<(String, BTreeSet<String>)> = Vec::new();
+    for entry in fs::read_dir(dir).expect("i18n directory missing").flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let mut keys = BTreeSet::new();
+        for file in fs::read_dir(&path).into_iter().flatten().flatten() {
+            let file = file.path();
+            if file.extension().is_some_and(|e| e == "ftl") {
+                println!("cargo:rerun-if-changed={}", file.display());
+                let content = fs::read_to_string(&file).expect("unreadable ftl file");
+                keys.extend(message_ids(&content));
+            }
+        }
+        locales.push((entry.file_name().to_string_lossy().to_string(), keys));
+    }
+
+    let all: BTreeSet<&String> = locales.iter().flat_map(|(_, keys)| keys).collect();
+    let mut problems = Vec::new();
+    for (locale, keys) in &locales {
+        let missing: Vec<&str> = all
+            .iter()
+            .filter(|key| !keys.contains(key.as_str()))
+            .map(|key| key.as_str())
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!("{} is missing: {}", locale, missing.join(", ")));
+        }
+    }
+    if !problems.is_empty() {
+        panic!("Incomplete translations\n{}", problems.join("\n"));
+    }
+}
+
+/// Ids of the messages and terms defined in a Fluent file: lines starting
+/// with an identifier followed by `=`. Attributes, comments and
+/// continuation lines are indented or start with other characters.
+fn message_ids(content: &str) -> impl Iterator<Item = String> + '_ {
+    content.lines().filter_map(|line| {
+        let (id, _) = line.split_once('=')?;
+        let id = id.trim_end();
+        let name = id.strip_prefix('-').unwrap_or(id);
+        let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| id.to_string())
+    })
+}
@@ -1,2 +1,89 @@
 tray-menu-show = Open InchBox
-tray-menu-quit = Quit
\ No newline at end of file
+tray-menu-quit = Quit
+
+# Command errors, see error.rs
+error-file-not-found = The file is no longer in the library
+error-files-not-found = { $count ->
+    [one] One file is no longer in the library
+   *[other] { $count } files are no longer in the library
+}
+error-path-not-found = File not found: { $path }
+error-tag-not-found = The tag no longer exists
+error-group-not-found = The group no longer exists
+error-duplicate-tag-name = A tag named "{ $name }" already exists
+error-invalid-tag-parent = A tag can't be moved under itself or one of its subtags
+error-tag-already-attached = The file already has this tag
+error-tag-not-attached = The file doesn't have this tag
+error-invalid-color = Invalid color: { $color }
+error-page-out-of-range = Page { $page } doesn't exist, the document has { $page_count } pages
+error-pdfium-unavailable = PDF previews are unavailable: { $reason }
+error-application-not-found = Application not found: { $app_id }
+error-unsupported-language = No translation for { $language }
+error-invalid-archive = Not a valid InchBox library archive: { $reason }
+error-unsupported-archive-version = This archive was created by a newer InchBox ({ $version })
+error-unknown-column = Unknown column: { $column }
+error-cancelled = Cancelled
+error-thumbnail = Failed to generate thumbnail: { $reason }
+error-save-thumbnail = Failed to save the thumbnail: { $reason }
+error-save-filmstrip = Failed to save the filmstrip: { $reason }
+error-save-page = Failed to save the page: { $reason }
+error-extractor-failed = { $extractor } could not read the file: { $reason }
+error-no-thumbnail-provider = No thumbnails for .{ $extension } files
+
+# Reading files for thumbnails and metadata, see thumbnail/
+error-decode-image = Failed to decode the image: { $reason }
+error-decode-raw = Failed to decode the RAW file: { $reason }
+error-invalid-raw-size = The RAW file decoded to an image of the wrong size
+error-read-exif = Failed to read the EXIF data: { $reason }
+error-load-pdf = Failed to load the PDF: { $reason }
+error-load-pdf-page = Failed to load page { $page }: { $reason }
+error-render-pdf-page = Failed to render page { $page }: { $reason }
+error-no-video-frame = None of the video's frames could be decoded
+error-unknown-video-duration = The video's duration is unknown
+error-build-filmstrip = Failed to build the filmstrip: { $reason }
+error-decode-frame = Failed to decode the video frame: { $reason }
+error-run-program = Failed to run { $program }: { $reason }
+error-program-timed-out = { $program } didn't finish within { $seconds } seconds
+error-program-failed = { $program } failed: { $reason }
+error-no-audio-samples = No audio could be decoded
+error-no-audio-track = The file has no audio track
+error-unsupported-audio-format = Unsupported audio format: { $reason }
+error-unsupported-audio-codec = Unsupported audio codec: { $reason }
+error-invalid-svg-size = The SVG has no usable size
+error-render-svg = Failed to render the SVG
+error-parse-svg = Failed to parse the SVG: { $reason }
+error-no-cover = The book declares no cover image
+error-no-package-document = The book has no package document
+error-no-embedded-thumbnail = The file has no embedded thumbnail
+error-binary-content = The file isn't text
+error-invalid-package = Not a valid package: { $reason }
+error-read-package-entry = Failed to read { $name } in the package: { $reason }
+error-shell-thumbnail = Windows couldn't provide a thumbnail: { $reason }
+error-shell-bitmap = Windows returned a thumbnail that couldn't be read
+error-no-cache-dir = No cache directory
+
+# Desktop integration
+error-no-files = No files selected
+error-not-supported = Not supported on this operating system
+error-open-failed = Failed to open the file: { $reason }
+error-launch-failed = Failed to launch { $app }: { $reason }
+error-invalid-exec = Invalid Exec line in the entry of { $app_id }
+error-invalid-exec-quote = Unterminated quote in an Exec line
+error-reveal-failed = Failed to show the files: { $reason }
+error-clipboard-unavailable = The clipboard is unavailable: { $reason }
+error-copy-files-failed = Failed to copy files: { $reason }
+
+# Result of adding files, see AddFilesResponse
+add-files-added = { $count ->
+    [one] Added one file.
+   *[other] Added { $count } files.
+}
+add-files-none = No files added.
+add-files-skipped-duplicates = { $count ->
+    [one] Skipped one file already in the library.
+   *[other] Skipped { $count } files already in the library.
+}
+add-files-tag-conflicts = { $count ->
+    [one] Left out one keyword whose tag name is already used elsewhere.
+   *[other] Left out { $count } keywords whose tag names are already used elsewhere.
+}
@@ -1,2 +1,77 @@
 tray-menu-show = 打开 InchBox
-tray-menu-quit = 退出
\ No newline at end of file
+tray-menu-quit = 退出
+
+# Command errors, see error.rs
+error-file-not-found = 文件已不在库中
+error-files-not-found = { $count } 个文件已不在库中
+error-path-not-found = 找不到文件：{ $path }
+error-tag-not-found = 标签已不存在
+error-group-not-found = 分组已不存在
+error-duplicate-tag-name = 已存在名为“{ $name }”的标签
+error-invalid-tag-parent = 不能将标签移动到自身或其子标签下
+error-tag-already-attached = 文件已有此标签
+error-tag-not-attached = 文件没有此标签
+error-invalid-color = 无效的颜色：{ $color }
+error-page-out-of-range = 第 { $page } 页不存在，文档共 { $page_count } 页
+error-pdfium-unavailable = PDF 预览不可用：{ $reason }
+error-application-not-found = 找不到应用程序：{ $app_id }
+error-unsupported-language = 不支持的语言：{ $language }
+error-invalid-archive = 不是有效的 InchBox 资料库归档：{ $reason }
+error-unsupported-archive-version = 此归档由更新版本的 InchBox（{ $version }）创建
+error-unknown-column = 未知的列：{ $column }
+error-cancelled = 已取消
+error-thumbnail = 生成缩略图失败：{ $reason }
+error-save-thumbnail = 保存缩略图失败：{ $reason }
+error-save-filmstrip = 保存胶片条失败：{ $reason }
+error-save-page = 保存页面失败：{ $reason }
+error-extractor-failed = { $extractor } 无法读取文件：{ $reason }
+error-no-thumbnail-provider = 无法为 .{ $extension } 文件生成缩略图
+
+# Reading files for thumbnails and metadata, see thumbnail/
+error-decode-image = 解码图片失败：{ $reason }
+error-decode-raw = 解码 RAW 文件失败：{ $reason }
+error-invalid-raw-size = RAW 文件解码出的图片尺寸不正确
+error-read-exif = 读取 EXIF 数据失败：{ $reason }
+error-load-pdf = 加载 PDF 失败：{ $reason }
+error-load-pdf-page = 加载第 { $page } 页失败：{ $reason }
+error-render-pdf-page = 渲染第 { $page } 页失败：{ $reason }
+error-no-video-frame = 视频的所有帧都无法解码
+error-unknown-video-duration = 视频时长未知
+error-build-filmstrip = 生成胶片条失败：{ $reason }
+error-decode-frame = 解码视频帧失败：{ $reason }
+error-run-program = 运行 { $program } 失败：{ $reason }
+error-program-timed-out = { $program } 未在 { $seconds } 秒内完成
+error-program-failed = { $program } 执行失败：{ $reason }
+error-no-audio-samples = 无法解码任何音频
+error-no-audio-track = 文件中没有音轨
+error-unsupported-audio-format = 不支持的音频格式：{ $reason }
+error-unsupported-audio-codec = 不支持的音频编码：{ $reason }
+error-invalid-svg-size = SVG 没有可用的尺寸
+error-render-svg = 渲染 SVG 失败
+error-parse-svg = 解析 SVG 失败：{ $reason }
+error-no-cover = 电子书没有声明封面图片
+error-no-package-document = 电子书缺少包文档
+error-no-embedded-thumbnail = 文件中没有内嵌缩略图
+error-binary-content = 文件不是文本
+error-invalid-package = 不是有效的文件包：{ $reason }
+error-read-package-entry = 读取文件包中的 { $name } 失败：{ $reason }
+error-shell-thumbnail = Windows 无法提供缩略图：{ $reason }
+error-shell-bitmap = Windows 返回的缩略图无法读取
+error-no-cache-dir = 没有缓存目录
+
+# Desktop integration
+error-no-files = 未选择文件
+error-not-supported = 当前操作系统不支持此功能
+error-open-failed = 打开文件失败：{ $reason }
+error-launch-failed = 启动 { $app } 失败：{ $reason }
+error-invalid-exec = { $app_id } 的 Exec 行无效
+error-invalid-exec-quote = Exec 行中的引号未闭合
+error-reveal-failed = 显示文件失败：{ $reason }
+error-clipboard-unavailable = 剪贴板不可用：{ $reason }
+error-copy-files-failed = 复制文件失败：{ $reason }
+
+# Result of adding files, see AddFilesResponse
+add-files-added = 已添加 { $count } 个文件。
+add-files-none = 未添加任何文件。
+add-files-skipped-duplicates = 跳过 { $count } 个已在库中的文件。
+add-files-tag-conflicts = 忽略了 { $count } 个关键词，其标签名已在别处使用。
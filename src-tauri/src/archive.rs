@@ -0,0 +1,493 @@
+use crate::cache::ThumbnailCache;
+use crate::error::InchBoxError;
+use crate::models::{AppData, FileItem, Tag, TagGroup, CURRENT_VERSION};
+use crate::thumbnail::ThumbnailFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Identifies InchBox library archives in the manifest
+const FORMAT: &str = "inchbox-library";
+
+/// Bumped when the archive layout changes incompatibly. The library itself
+/// carries its own schema version.
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const LIBRARY: &str = "library.json";
+const THUMBNAIL_INDEX: &str = "thumbnails/index.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Manifest {
+    pub format: String,
+    pub format_version: u32,
+    pub app_version: String,
+    pub schema_version: u32,
+    pub exported_at: String,
+    pub file_count: usize,
+    pub tag_count: usize,
+    pub group_count: usize,
+    pub thumbnail_count: usize,
+}
+
+/// A cached thumbnail stored in the archive. The source file's size and
+/// modification time are kept so the thumbnail stays valid after import
+/// when the files were copied with their timestamps.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThumbnailEntry {
+    pub file_id: String,
+    /// Size tier part of the cache key, e.g. `320` or `filmstrip`
+    pub variant: String,
+    pub format: ThumbnailFormat,
+    pub source_size: u64,
+    pub source_modified_time: i64,
+}
+
+/// Variant of the filmstrip sprite
+const FILMSTRIP_VARIANT: &str = "filmstrip";
+
+/// A size tier (`320`) or the filmstrip. Anything else, page renders
+/// included, isn't exported, and the index of an archive being imported is
+/// untrusted: the variant becomes part of a path in the cache directory.
+fn is_thumbnail_variant(variant: &str) -> bool {
+    variant == FILMSTRIP_VARIANT
+        || (!variant.is_empty() && variant.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Ids end up in cache file names, so only UUID characters are accepted.
+fn is_plain_id(id: &str) -> bool {
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+impl ThumbnailEntry {
+    fn archive_name(&self) -> String {
+        format!(
+            "thumbnails/{}_{}.{}",
+            self.file_id,
+            self.variant,
+            self.format.extension()
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Add to the current library
+    #[default]
+    Merge,
+    /// Discard the current library and use the archive's
+    Replace,
+}
+
+/// What to do when an imported tag has the name of an existing one.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagConflict {
+    /// Treat them as the same tag
+    #[default]
+    Merge,
+    /// Keep both, numbering the imported one: `Travel (2)`
+    Rename,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TagRename {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct ImportReport {
+    pub files_added: usize,
+    /// Files already in the library under the same path; their tags are combined
+    pub files_merged: usize,
+    /// Imported files whose path doesn't exist on this machine
+    pub files_missing: usize,
+    pub tags_added: usize,
+    pub tags_merged: usize,
+    pub tags_renamed: Vec<TagRename>,
+    pub groups_added: usize,
+    pub groups_merged: usize,
+    pub thumbnails_imported: usize,
+}
+
+fn invalid(message: impl Into<String>) -> InchBoxError {
+    InchBoxError::InvalidArchive {
+        message: message.into(),
+    }
+}
+
+/// The cached thumbnails of the files in `data` that go into an archive,
+/// with the cache files holding them. Only reads the cache index, so the
+/// caller can release the cache before the files are read.
+pub fn thumbnail_entries(data: &AppData, cache: &ThumbnailCache) -> Vec<(ThumbnailEntry, PathBuf)> {
+    let mut thumbnails = Vec::new();
+    for file in &data.files {
+        for (key, path, meta) in cache.file_entries(&file.id) {
+            let Some(variant) = key.strip_prefix(&format!("{}_", file.id)) else {
+                continue;
+            };
+            if is_thumbnail_variant(variant) {
+                let entry = ThumbnailEntry {
+                    file_id: file.id.clone(),
+                    variant: variant.to_string(),
+                    format: meta.format,
+                    source_size: meta.size,
+                    source_modified_time: meta.modified_time,
+                };
+                thumbnails.push((entry, path));
+            }
+        }
+    }
+    thumbnails
+}
+
+/// Write `data` and the given thumbnails, from `thumbnail_entries`, to a new
+/// archive at `destination`. Thumbnails whose file is gone are left out.
+pub fn export(
+    destination: &Path,
+    data: &AppData,
+    mut thumbnails: Vec<(ThumbnailEntry, PathBuf)>,
+) -> io::Result<Manifest> {
+    thumbnails.retain(|(_, path)| path.exists());
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        format_version: FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version: data.version,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        file_count: data.files.len(),
+        tag_count: data.tags.len(),
+        group_count: data.groups.len(),
+        thumbnail_count: thumbnails.len(),
+    };
+
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Thumbnails are compressed images already
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut zip = ZipWriter::new(File::create(destination)?);
+
+    zip.start_file(MANIFEST, deflated)?;
+    serde_json::to_writer_pretty(&mut zip, &manifest).map_err(io::Error::other)?;
+    zip.start_file(LIBRARY, deflated)?;
+    serde_json::to_writer(&mut zip, data).map_err(io::Error::other)?;
+
+    if !thumbnails.is_empty() {
+        let index: Vec<&ThumbnailEntry> = thumbnails.iter().map(|(entry, _)| entry).collect();
+        zip.start_file(THUMBNAIL_INDEX, deflated)?;
+        serde_json::to_writer(&mut zip, &index).map_err(io::Error::other)?;
+        for (entry, path) in &thumbnails {
+            zip.start_file(entry.archive_name(), stored)?;
+            zip.write_all(&fs::read(path)?)?;
+        }
+    }
+
+    zip.finish()?;
+    Ok(manifest)
+}
+
+/// An archive opened for import, checked to be one this version can read.
+pub struct LibraryArchive {
+    pub manifest: Manifest,
+    pub thumbnails: Vec<ThumbnailEntry>,
+    zip: ZipArchive<File>,
+}
+
+impl LibraryArchive {
+    /// Open the archive at `path` and read its library.
+    pub fn open(path: &Path) -> Result<(Self, AppData), InchBoxError> {
+        let file = File::open(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => InchBoxError::PathNotFound {
+                path: path.to_string_lossy().to_string(),
+            },
+            _ => e.into(),
+        })?;
+        let mut zip = ZipArchive::new(file).map_err(|e| invalid(e.to_string()))?;
+
+        let manifest: Manifest = read_json(&mut zip, MANIFEST)?;
+        if manifest.format != FORMAT {
+            return Err(invalid(format!("Unknown format {}", manifest.format)));
+        }
+        if manifest.format_version > FORMAT_VERSION || manifest.schema_version > CURRENT_VERSION {
+            return Err(InchBoxError::UnsupportedArchiveVersion {
+                version: manifest.app_version.clone(),
+            });
+        }
+        let data: AppData = read_json(&mut zip, LIBRARY)?;
+        let mut thumbnails: Vec<ThumbnailEntry> = if zip.index_for_name(THUMBNAIL_INDEX).is_some() {
+            read_json(&mut zip, THUMBNAIL_INDEX)?
+        } else {
+            Vec::new()
+        };
+        thumbnails.retain(|entry| {
+            let valid = is_plain_id(&entry.file_id) && is_thumbnail_variant(&entry.variant);
+            if !valid {
+                tracing::warn!(
+                    file_id = %entry.file_id,
+                    variant = %entry.variant,
+                    "Skipping invalid thumbnail entry"
+                );
+            }
+            valid
+        });
+
+        let archive = Self {
+            manifest,
+            thumbnails,
+            zip,
+        };
+        Ok((archive, data))
+    }
+
+    /// Copy the thumbnails of imported files into the cache under their new
+    /// ids. `file_ids` maps archive ids to library ids, others are skipped;
+    /// `paths` has the source path of each library id. The cache is only
+    /// locked to look up and record entries, not while decompressing.
+    pub fn import_thumbnails(
+        &mut self,
+        file_ids: &HashMap<String, String>,
+        paths: &HashMap<String, String>,
+        cache: &Mutex<ThumbnailCache>,
+    ) -> usize {
+        let mut imported = 0;
+        for entry in &self.thumbnails {
+            let Some(new_id) = file_ids.get(&entry.file_id) else {
+                continue;
+            };
+            let Some(source) = paths.get(new_id) else {
+                continue;
+            };
+            let key = format!("{}_{}", new_id, entry.variant);
+            let target = cache.lock().unwrap().thumb_path(&key, entry.format);
+            let copied = self
+                .zip
+                .by_name(&entry.archive_name())
+                .map_err(io::Error::from)
+                .and_then(|mut file| {
+                    let mut bytes = Vec::new();
+                    file.read_to_end(&mut bytes)?;
+                    fs::write(&target, bytes)
+                });
+            match copied {
+                Ok(()) => {
+                    cache.lock().unwrap().update_metadata(
+                        &key,
+                        new_id,
+                        Path::new(source),
+                        entry.source_size,
+                        entry.source_modified_time,
+                        entry.format,
+                    );
+                    imported += 1;
+                }
+                Err(e) => tracing::warn!(key = %key, error = %e, "Failed to import thumbnail"),
+            }
+        }
+        imported
+    }
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(
+    zip: &mut ZipArchive<File>,
+    name: &str,
+) -> Result<T, InchBoxError> {
+    let file = zip
+        .by_name(name)
+        .map_err(|_| invalid(format!("{} is missing", name)))?;
+    serde_json::from_reader(file).map_err(|e| invalid(format!("{}: {}", name, e)))
+}
+
+/// Drop references to tags and groups that don't exist, so a hand-edited
+/// or partial archive can't leave dangling ids behind.
+fn sanitize(data: &mut AppData) {
+    let group_ids: HashSet<String> = data.groups.iter().map(|g| g.id.clone()).collect();
+    let tag_ids: HashSet<String> = data.tags.iter().map(|t| t.id.clone()).collect();
+    for tag in data.tags.iter_mut() {
+        if tag
+            .group_id
+            .as_ref()
+            .is_some_and(|id| !group_ids.contains(id))
+        {
+            tag.group_id = None;
+        }
+        if tag
+            .parent_id
+            .as_ref()
+            .is_some_and(|id| !tag_ids.contains(id))
+        {
+            tag.parent_id = None;
+        }
+    }
+    for file in data.files.iter_mut() {
+        file.tag_ids.retain(|id| tag_ids.contains(id));
+    }
+}
+
+/// Make the archive's library the current one. Ids are kept, so every file
+/// maps to itself for the thumbnail import, except ids that couldn't have
+/// come from InchBox: those files get fresh ones.
+pub fn replace(
+    current: &mut AppData,
+    mut incoming: AppData,
+) -> (ImportReport, HashMap<String, String>) {
+    sanitize(&mut incoming);
+    let report = ImportReport {
+        files_added: incoming.files.len(),
+        tags_added: incoming.tags.len(),
+        groups_added: incoming.groups.len(),
+        ..Default::default()
+    };
+    let mut file_ids = HashMap::new();
+    for file in incoming.files.iter_mut() {
+        let archive_id = file.id.clone();
+        if !is_plain_id(&file.id) {
+            file.id = Uuid::new_v4().to_string();
+        }
+        file_ids.insert(archive_id, file.id.clone());
+    }
+
+    current.files = incoming.files;
+    current.tags = incoming.tags;
+    current.groups = incoming.groups;
+    (report, file_ids)
+}
+
+/// Add the archive's library to the current one under fresh ids. Groups
+/// with the same name are combined, tags by `conflict`, and files already
+/// present under the same path get the union of their tags. Returns the
+/// report and the archive-to-library ids of newly added files.
+pub fn merge(
+    current: &mut AppData,
+    mut incoming: AppData,
+    conflict: TagConflict,
+) -> (ImportReport, HashMap<String, String>) {
+    sanitize(&mut incoming);
+    let mut report = ImportReport::default();
+
+    let mut group_ids: HashMap<String, String> = HashMap::new();
+    for group in incoming.groups {
+        match current
+            .groups
+            .iter()
+            .find(|g| g.name.eq_ignore_ascii_case(&group.name))
+        {
+            Some(existing) => {
+                group_ids.insert(group.id, existing.id.clone());
+                report.groups_merged += 1;
+            }
+            None => {
+                let id = Uuid::new_v4().to_string();
+                group_ids.insert(group.id, id.clone());
+                current.groups.push(TagGroup { id, ..group });
+                report.groups_added += 1;
+            }
+        }
+    }
+
+    // Parents are resolved once every tag has its new id
+    let mut tag_ids: HashMap<String, String> = HashMap::new();
+    let mut added: Vec<(Tag, Option<String>)> = Vec::new();
+    for tag in incoming.tags {
+        let existing = current
+            .tags
+            .iter()
+            .chain(added.iter().map(|(t, _)| t))
+            .find(|t| t.name.eq_ignore_ascii_case(&tag.name))
+            .map(|t| t.id.clone());
+        let name = match (existing, conflict) {
+            (Some(existing), TagConflict::Merge) => {
+                tag_ids.insert(tag.id, existing);
+                report.tags_merged += 1;
+                continue;
+            }
+            (Some(_), TagConflict::Rename) => {
+                let renamed = unique_tag_name(&tag.name, current, &added);
+                report.tags_renamed.push(TagRename {
+                    from: tag.name.clone(),
+                    to: renamed.clone(),
+                });
+                renamed
+            }
+            (None, _) => tag.name.clone(),
+        };
+        let id = Uuid::new_v4().to_string();
+        tag_ids.insert(tag.id, id.clone());
+        let new_tag = Tag {
+            id,
+            name,
+            parent_id: None,
+            group_id: tag.group_id.and_then(|g| group_ids.get(&g).cloned()),
+        };
+        added.push((new_tag, tag.parent_id));
+    }
+    report.tags_added = added.len();
+    for (mut tag, parent_id) in added {
+        tag.parent_id = parent_id.and_then(|p| tag_ids.get(&p).cloned());
+        current.tags.push(tag);
+    }
+
+    let mut file_ids: HashMap<String, String> = HashMap::new();
+    for file in incoming.files {
+        let imported_tags: Vec<String> = file
+            .tag_ids
+            .iter()
+            .filter_map(|id| tag_ids.get(id).cloned())
+            .collect();
+        match current.files.iter_mut().find(|f| f.path == file.path) {
+            Some(existing) => {
+                for tag_id in imported_tags {
+                    if !existing.tag_ids.contains(&tag_id) {
+                        existing.tag_ids.push(tag_id);
+                    }
+                }
+                report.files_merged += 1;
+            }
+            None => {
+                let id = Uuid::new_v4().to_string();
+                file_ids.insert(file.id.clone(), id.clone());
+                let mut tags: Vec<String> = Vec::new();
+                for tag_id in imported_tags {
+                    if !tags.contains(&tag_id) {
+                        tags.push(tag_id);
+                    }
+                }
+                current.files.push(FileItem {
+                    id,
+                    tag_ids: tags,
+                    ..file
+                });
+                report.files_added += 1;
+            }
+        }
+    }
+
+    (report, file_ids)
+}
+
+/// `Name (2)`, `Name (3)`... whichever is free first.
+fn unique_tag_name(name: &str, current: &AppData, added: &[(Tag, Option<String>)]) -> String {
+    let taken = |candidate: &str| {
+        current
+            .tags
+            .iter()
+            .chain(added.iter().map(|(t, _)| t))
+            .any(|t| t.name.eq_ignore_ascii_case(candidate))
+    };
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// How many of `paths` don't exist on this machine.
+pub fn count_missing<'a>(paths: impl Iterator<Item = &'a String>) -> usize {
+    paths.filter(|path| !Path::new(path).exists()).count()
+}
@@ -1,8 +1,19 @@
+use crate::thumbnail::{ThumbnailFormat, ThumbnailSize};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long metadata changes may sit in memory before being written out
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Unreferenced thumbnails younger than this may belong to a job in flight
+const ORPHAN_GRACE_PERIOD: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ThumbnailMetadata {
@@ -10,73 +21,234 @@ pub struct ThumbnailMetadata {
     pub path: String,
     pub size: u64,
     pub modified_time: i64,
+    /// Encoding of the thumbnail file; entries from before size tiers are JPEG
+    #[serde(default)]
+    pub format: ThumbnailFormat,
+    /// Size of the thumbnail file itself, in bytes
+    #[serde(default)]
+    pub thumb_size: u64,
+    /// Unix millis of the last time the thumbnail was served
+    #[serde(default)]
+    pub last_accessed: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CacheStats {
+    pub entry_count: usize,
+    pub total_bytes: u64,
+    pub max_bytes: u64,
+    pub cache_dir: String,
 }
 
 pub struct ThumbnailCache {
     metadata: HashMap<String, ThumbnailMetadata>,
     cache_dir: PathBuf,
+    dirty: bool,
+    max_bytes: u64,
 }
 
 impl ThumbnailCache {
-    pub fn new(cache_dir: &Path) -> Result<Self, String> {
+    pub fn new(cache_dir: &Path) -> Self {
         let mut cache = Self {
             metadata: HashMap::new(),
             cache_dir: cache_dir.to_path_buf(),
+            dirty: false,
+            max_bytes: u64::MAX,
         };
 
         // Try to load existing metadata
         if let Err(e) = cache.load() {
-            println!("Failed to load cache metadata: {}", e);
+            tracing::warn!(error = %e, "Failed to load cache metadata");
         }
 
-        Ok(cache)
+        cache
+    }
+
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// One entry per file and size tier, e.g. `<uuid>_320`.
+    pub fn get_cache_key(file_id: &str, size: ThumbnailSize) -> String {
+        format!("{}_{}", file_id, size.pixels())
+    }
+
+    /// Where the thumbnail for `key` is stored once encoded as `format`.
+    pub fn thumb_path(&self, key: &str, format: ThumbnailFormat) -> PathBuf {
+        self.cache_dir
+            .join(format!("{}.{}", key, format.extension()))
     }
 
-    pub fn get_cache_key(file_id: &str) -> String {
-        file_id.to_string()
+    fn entry_path(&self, key: &str) -> Option<PathBuf> {
+        self.metadata
+            .get(key)
+            .map(|meta| self.thumb_path(key, meta.format))
     }
 
-    pub fn is_valid(&self, file_id: &str, path: &Path, size: u64, modified_time: i64) -> bool {
-        if let Some(meta) = self.metadata.get(file_id) {
-            // Check if file metadata matches
-            if meta.path == path.to_string_lossy().to_string()
-                && meta.size == size
-                && meta.modified_time == modified_time
-            {
-                // Check if thumbnail file exists
-                let thumb_path = self.cache_dir.join(format!("{}.jpg", file_id));
-                return thumb_path.exists();
+    pub fn set_max_bytes(&mut self, max_bytes: u64) {
+        self.max_bytes = max_bytes;
+    }
+
+    /// Path of the cached thumbnail for `key` if it is still up to date with
+    /// the source file.
+    pub fn get_valid(
+        &self,
+        key: &str,
+        path: &Path,
+        size: u64,
+        modified_time: i64,
+    ) -> Option<PathBuf> {
+        let meta = self.metadata.get(key)?;
+        // Check if file metadata matches
+        if meta.path == path.to_string_lossy()
+            && meta.size == size
+            && meta.modified_time == modified_time
+        {
+            // Check if thumbnail file exists
+            let thumb_path = self.thumb_path(key, meta.format);
+            if thumb_path.exists() {
+                return Some(thumb_path);
             }
         }
-        false
+        None
     }
 
-    pub fn update_metadata(&mut self, file_id: &str, path: &Path, size: u64, modified_time: i64) {
+    pub fn update_metadata(
+        &mut self,
+        key: &str,
+        file_id: &str,
+        path: &Path,
+        size: u64,
+        modified_time: i64,
+        format: ThumbnailFormat,
+    ) {
+        // A re-render may have switched format; drop the stale file
+        if let Some(old_path) = self.entry_path(key) {
+            if old_path != self.thumb_path(key, format) {
+                let _ = fs::remove_file(old_path);
+            }
+        }
+
         let meta = ThumbnailMetadata {
             file_id: file_id.to_string(),
             path: path.to_string_lossy().to_string(),
             size,
             modified_time,
+            format,
+            thumb_size: fs::metadata(self.thumb_path(key, format))
+                .map(|m| m.len())
+                .unwrap_or(0),
+            last_accessed: Utc::now().timestamp_millis(),
         };
-        self.metadata.insert(file_id.to_string(), meta);
+        self.metadata.insert(key.to_string(), meta);
+        self.dirty = true;
     }
 
-    pub fn remove_metadata(&mut self, file_id: &str) {
-        self.metadata.remove(file_id);
+    /// Record that a cached thumbnail was served, for LRU eviction.
+    pub fn touch(&mut self, key: &str) {
+        if let Some(meta) = self.metadata.get_mut(key) {
+            meta.last_accessed = Utc::now().timestamp_millis();
+            self.dirty = true;
+        }
     }
 
-    pub fn save(&self) -> Result<(), String> {
-        let metadata_path = self.cache_dir.join("metadata.json");
+    pub fn total_bytes(&self) -> u64 {
+        self.metadata.values().map(|m| m.thumb_size).sum()
+    }
 
-        // Create parent directory if needed
-        if let Some(parent) = metadata_path.parent() {
-            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    /// Drop least recently used entries until the cache fits in `max_bytes`.
+    /// Returns the thumbnail files of the dropped entries, for the caller to
+    /// delete once the cache is unlocked.
+    pub fn evict_to_limit(&mut self) -> Vec<PathBuf> {
+        let mut total = self.total_bytes();
+        if total <= self.max_bytes {
+            return Vec::new();
         }
 
-        let json = serde_json::to_string_pretty(&self.metadata).map_err(|e| e.to_string())?;
+        let mut by_age: Vec<(i64, String, u64)> = self
+            .metadata
+            .iter()
+            .map(|(key, m)| (m.last_accessed, key.clone(), m.thumb_size))
+            .collect();
+        by_age.sort();
 
-        fs::write(&metadata_path, json).map_err(|e| e.to_string())?;
-        Ok(())
+        let mut evicted = Vec::new();
+        for (_, key, thumb_size) in by_age {
+            if total <= self.max_bytes {
+                break;
+            }
+            if let Some(thumb_path) = self.entry_path(&key) {
+                evicted.push(thumb_path);
+            }
+            self.metadata.remove(&key);
+            total = total.saturating_sub(thumb_size);
+        }
+
+        if !evicted.is_empty() {
+            self.dirty = true;
+            tracing::info!(
+                evicted = evicted.len(),
+                "Evicted thumbnails to fit the cache size limit"
+            );
+        }
+        evicted
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entry_count: self.metadata.len(),
+            total_bytes: self.total_bytes(),
+            max_bytes: self.max_bytes,
+            cache_dir: self.cache_dir.to_string_lossy().to_string(),
+        }
+    }
+
+    /// Forget every cached thumbnail. The files are left to
+    /// [`remove_thumbnail_files`] once the cache is unlocked.
+    pub fn clear(&mut self) {
+        self.metadata.clear();
+        self.dirty = true;
+    }
+
+    /// Cached thumbnails of `file_id` as key, thumbnail file and metadata.
+    pub fn file_entries(&self, file_id: &str) -> Vec<(String, PathBuf, ThumbnailMetadata)> {
+        self.metadata
+            .iter()
+            .filter(|(_, meta)| meta.file_id == file_id)
+            .map(|(key, meta)| (key.clone(), self.thumb_path(key, meta.format), meta.clone()))
+            .collect()
+    }
+
+    /// Drop every size tier cached for `file_id`. Returns their thumbnail
+    /// files, for the caller to delete once the cache is unlocked.
+    pub fn remove_file_entries(&mut self, file_id: &str) -> Vec<PathBuf> {
+        let keys: Vec<String> = self
+            .metadata
+            .iter()
+            .filter(|(_, meta)| meta.file_id == file_id)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut removed = Vec::new();
+        for key in keys {
+            if let Some(thumb_path) = self.entry_path(&key) {
+                removed.push(thumb_path);
+            }
+            self.metadata.remove(&key);
+            self.dirty = true;
+        }
+        removed
+    }
+
+    /// Serialized metadata if it changed since the last call. The caller
+    /// writes it with [`write_metadata`] and marks the cache dirty again if
+    /// that fails.
+    fn take_pending(&mut self) -> Result<Option<String>, String> {
+        if !self.dirty {
+            return Ok(None);
+        }
+        let json = serde_json::to_string_pretty(&self.metadata).map_err(|e| e.to_string())?;
+        self.dirty = false;
+        Ok(Some(json))
     }
 
     pub fn load(&mut self) -> Result<(), String> {
@@ -96,42 +268,212 @@ impl ThumbnailCache {
         Ok(())
     }
 
-    pub fn cleanup_orphaned_thumbnails(
+    /// Drop entries whose file is no longer in the library. Returns the
+    /// thumbnails the remaining entries refer to, for an orphan sweep.
+    fn retain_files(&mut self, valid_file_ids: &HashSet<String>) -> HashSet<PathBuf> {
+        let before = self.metadata.len();
+        self.metadata
+            .retain(|_, meta| valid_file_ids.contains(&meta.file_id));
+        if self.metadata.len() != before {
+            self.dirty = true;
+        }
+        self.metadata
+            .keys()
+            .filter_map(|key| self.entry_path(key))
+            .collect()
+    }
+
+    /// Check what a sweep of the directory found against the entries again,
+    /// since thumbnails may have been rendered during the sweep. Returns the
+    /// `orphans` still unreferenced, and the entries whose thumbnail was not
+    /// among `present` with the file they expect.
+    fn unreferenced(
         &self,
-        thumb_dir: &Path,
-        valid_file_ids: &[String],
-        limit: usize,
-    ) -> Result<usize, String> {
-        use std::collections::HashSet;
+        orphans: Vec<PathBuf>,
+        present: &HashSet<PathBuf>,
+    ) -> (Vec<PathBuf>, Vec<(String, PathBuf)>) {
+        let referenced: HashSet<PathBuf> = self
+            .metadata
+            .keys()
+            .filter_map(|key| self.entry_path(key))
+            .collect();
+        let orphans = orphans
+            .into_iter()
+            .filter(|path| !referenced.contains(path))
+            .collect();
+        let unseen = self
+            .metadata
+            .keys()
+            .filter_map(|key| Some((key.clone(), self.entry_path(key)?)))
+            .filter(|(_, path)| !present.contains(path))
+            .collect();
+        (orphans, unseen)
+    }
 
-        let valid_set: HashSet<String> = valid_file_ids.iter().cloned().collect();
+    /// Drop the entries whose thumbnail file turned out to be gone, unless
+    /// they were re-rendered in another format meanwhile.
+    fn remove_missing(&mut self, missing: &[(String, PathBuf)]) {
+        for (key, thumb_path) in missing {
+            if self.entry_path(key).as_ref() == Some(thumb_path) {
+                self.metadata.remove(key);
+                self.dirty = true;
+            }
+        }
+    }
+}
+
+/// Write `json` as the cache metadata in `cache_dir`.
+fn write_metadata(cache_dir: &Path, json: &str) -> Result<(), String> {
+    fs::create_dir_all(cache_dir).map_err(|e| e.to_string())?;
 
-        // Read thumbnail directory
-        let entries = fs::read_dir(thumb_dir).map_err(|e| e.to_string())?;
+    // Write to a sibling file and rename over the old one, so a crash
+    // mid-write never leaves a truncated metadata.json behind
+    let temp_path = cache_dir.join("metadata.json.tmp");
+    {
+        let mut file = fs::File::create(&temp_path).map_err(|e| e.to_string())?;
+        file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
+        file.sync_all().map_err(|e| e.to_string())?;
+    }
+    fs::rename(&temp_path, cache_dir.join("metadata.json")).map_err(|e| e.to_string())?;
+    Ok(())
+}
 
-        let mut removed_count = 0;
-        for entry in entries.flatten().take(limit) {
-            let path = entry.path();
+/// Read the cache directory without holding the cache lock. Returns the
+/// thumbnails outside `referenced` that are old enough to be orphans, and
+/// every thumbnail present.
+fn scan_orphans(
+    cache_dir: &Path,
+    referenced: &HashSet<PathBuf>,
+) -> Result<(Vec<PathBuf>, HashSet<PathBuf>), String> {
+    let entries = fs::read_dir(cache_dir).map_err(|e| e.to_string())?;
 
-            // Only process .jpg files
-            if path.extension().and_then(|e| e.to_str()) != Some("jpg") {
-                continue;
+    let mut orphans = Vec::new();
+    let mut present = HashSet::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !is_thumbnail_file(&path) {
+            continue;
+        }
+        if !referenced.contains(&path) {
+            // Leave files alone that may still be waiting for their metadata
+            let is_recent = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|age| age < ORPHAN_GRACE_PERIOD);
+            if !is_recent {
+                orphans.push(path.clone());
             }
+        }
+        present.insert(path);
+    }
+    Ok((orphans, present))
+}
 
-            // Extract file_id from filename (e.g., "uuid.jpg" -> "uuid")
-            if let Some(file_id) = path.file_stem().and_then(|s| s.to_str()) {
-                // If file_id is not in valid files, remove the thumbnail
-                if !valid_set.contains(file_id) {
-                    let _ = fs::remove_file(&path);
-                    removed_count += 1;
+/// Delete every thumbnail file in `cache_dir`, after [`ThumbnailCache::clear`].
+/// Returns how many files were removed.
+pub fn remove_thumbnail_files(cache_dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| is_thumbnail_file(path) && fs::remove_file(path).is_ok())
+        .count()
+}
+
+fn is_thumbnail_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| ThumbnailFormat::from_extension(ext).is_some())
+}
+
+/// The process-wide thumbnail cache, shared by all commands. Changes are
+/// batched in memory and written out by a background thread. Disk work
+/// happens outside the cache lock, so serving thumbnails never waits on it.
+#[derive(Clone)]
+pub struct ThumbnailCacheState {
+    pub cache: Arc<Mutex<ThumbnailCache>>,
+    /// Held while writing metadata, so an older snapshot can't land last
+    writing: Arc<Mutex<()>>,
+}
+
+impl ThumbnailCacheState {
+    pub fn new(cache_dir: &Path, max_bytes: u64) -> Self {
+        let mut cache = ThumbnailCache::new(cache_dir);
+        cache.set_max_bytes(max_bytes);
+        let state = Self {
+            cache: Arc::new(Mutex::new(cache)),
+            writing: Arc::new(Mutex::new(())),
+        };
+
+        // Eviction rides along with the periodic flush
+        let flusher = state.clone();
+        thread::spawn(move || loop {
+            thread::sleep(FLUSH_INTERVAL);
+            let evicted = flusher.cache.lock().unwrap().evict_to_limit();
+            for thumb_path in evicted {
+                let _ = fs::remove_file(thumb_path);
+            }
+            flusher.flush();
+        });
+
+        state
+    }
+
+    /// Sweep the whole thumbnail directory for orphans on a background
+    /// thread. The cache is only locked to snapshot and to apply the result;
+    /// files are read and deleted without it.
+    pub fn sweep_orphans(&self, valid_file_ids: Vec<String>) {
+        let cache = self.cache.clone();
+        thread::spawn(move || {
+            let valid_set: HashSet<String> = valid_file_ids.into_iter().collect();
+            let (cache_dir, referenced) = {
+                let mut cache = cache.lock().unwrap();
+                (cache.cache_dir.clone(), cache.retain_files(&valid_set))
+            };
+            let (orphans, present) = match scan_orphans(&cache_dir, &referenced) {
+                Ok(found) => found,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to sweep the thumbnail cache");
+                    return;
                 }
+            };
+            let (orphans, unseen) = cache.lock().unwrap().unreferenced(orphans, &present);
+            let removed = orphans
+                .iter()
+                .filter(|path| fs::remove_file(path).is_ok())
+                .count();
+            let missing: Vec<(String, PathBuf)> = unseen
+                .into_iter()
+                .filter(|(_, thumb_path)| !thumb_path.exists())
+                .collect();
+            if !missing.is_empty() {
+                cache.lock().unwrap().remove_missing(&missing);
             }
-        }
+            if removed > 0 {
+                tracing::info!(removed, "Cleaned up orphaned thumbnails");
+            }
+        });
+    }
 
-        if removed_count > 0 {
-            println!("Cleaned up {} orphaned thumbnails", removed_count);
+    /// Write pending changes immediately, e.g. before the app exits.
+    pub fn flush(&self) {
+        let _writing = self.writing.lock().unwrap();
+        let (pending, cache_dir) = {
+            let mut cache = self.cache.lock().unwrap();
+            (cache.take_pending(), cache.cache_dir.clone())
+        };
+        let result = match pending {
+            Ok(Some(json)) => write_metadata(&cache_dir, &json),
+            Ok(None) => return,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::error!(error = %e, "Failed to save cache metadata");
+            self.cache.lock().unwrap().dirty = true;
         }
-
-        Ok(removed_count)
     }
 }
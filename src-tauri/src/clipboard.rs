@@ -0,0 +1,260 @@
+use crate::i18n::fl;
+use std::path::PathBuf;
+
+/// Put `paths` on the system clipboard as files, so they can be pasted into
+/// a file manager.
+pub fn copy_files(paths: &[PathBuf]) -> Result<(), String> {
+    if paths.is_empty() {
+        return Err(fl!("error-no-files"));
+    }
+    platform::copy_files(paths)
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use crate::i18n::fl;
+    use std::path::PathBuf;
+
+    pub fn copy_files(paths: &[PathBuf]) -> Result<(), String> {
+        let paths: Vec<String> = paths
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        // Other applications may hold the clipboard open for a moment
+        let _clipboard = clipboard_win::Clipboard::new_attempts(10)
+            .map_err(|e| fl!("error-clipboard-unavailable", reason = e.to_string()))?;
+        clipboard_win::raw::empty().map_err(|e| e.to_string())?;
+        clipboard_win::raw::set_file_list(paths.as_slice())
+            .map_err(|e| fl!("error-copy-files-failed", reason = e.to_string()))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use crate::i18n::fl;
+    use crate::thumbnail::freedesktop::file_uri;
+    use std::path::PathBuf;
+
+    const URI_LIST: &str = "text/uri-list";
+    const GNOME_COPIED_FILES: &str = "x-special/gnome-copied-files";
+
+    pub fn copy_files(paths: &[PathBuf]) -> Result<(), String> {
+        let uris: Vec<String> = paths
+            .iter()
+            .map(|p| {
+                file_uri(p)
+                    .ok_or_else(|| fl!("error-path-not-found", path = p.display().to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        // text/uri-list lines end in CRLF (RFC 2483); GNOME's format is a
+        // verb followed by one URI per line
+        let contents = [
+            (URI_LIST, uris.join("\r\n").into_bytes()),
+            (
+                GNOME_COPIED_FILES,
+                format!("copy\n{}", uris.join("\n")).into_bytes(),
+            ),
+            (
+                "text/plain;charset=utf-8",
+                paths
+                    .iter()
+                    .map(|p| p.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .into_bytes(),
+            ),
+        ];
+
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            match wayland::copy(&contents) {
+                Ok(()) => return Ok(()),
+                // Compositors without the data-control protocol; XWayland
+                // clipboards are bridged, so fall through to X11
+                Err(e) => tracing::info!(error = %e, "Wayland clipboard unavailable, trying X11"),
+            }
+        }
+        x11::copy(&contents)
+    }
+
+    mod wayland {
+        use wl_clipboard_rs::copy::{MimeSource, MimeType, Options, Source};
+
+        /// Offer every format at once; wl-clipboard-rs serves the paste
+        /// requests from a background thread until another client copies.
+        pub fn copy(contents: &[(&str, Vec<u8>)]) -> Result<(), String> {
+            let sources = contents
+                .iter()
+                .map(|(mime_type, data)| MimeSource {
+                    source: Source::Bytes(data.clone().into_boxed_slice()),
+                    mime_type: MimeType::Specific(mime_type.to_string()),
+                })
+                .collect();
+            Options::new()
+                .copy_multi(sources)
+                .map_err(|e| e.to_string())
+        }
+    }
+
+    mod x11 {
+        use crate::i18n::fl;
+        use std::thread;
+        use x11rb::connection::Connection;
+        use x11rb::protocol::xproto::{
+            Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, EventMask, PropMode,
+            SelectionNotifyEvent, SelectionRequestEvent, WindowClass, SELECTION_NOTIFY_EVENT,
+        };
+        use x11rb::protocol::Event;
+        use x11rb::rust_connection::RustConnection;
+        use x11rb::wrapper::ConnectionExt as _;
+        use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+        /// Take ownership of the CLIPBOARD selection with a hidden window and
+        /// answer paste requests from a background thread until another
+        /// application takes it over.
+        pub fn copy(contents: &[(&str, Vec<u8>)]) -> Result<(), String> {
+            let (conn, screen_num) = x11rb::connect(None)
+                .map_err(|e| fl!("error-clipboard-unavailable", reason = e.to_string()))?;
+            let root = conn.setup().roots[screen_num].root;
+
+            let window = conn.generate_id().map_err(|e| e.to_string())?;
+            conn.create_window(
+                COPY_DEPTH_FROM_PARENT,
+                window,
+                root,
+                0,
+                0,
+                1,
+                1,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                COPY_FROM_PARENT,
+                &CreateWindowAux::new(),
+            )
+            .map_err(|e| e.to_string())?;
+
+            let clipboard = intern(&conn, "CLIPBOARD")?;
+            let targets = intern(&conn, "TARGETS")?;
+            let mut formats: Vec<(Atom, Vec<u8>)> = Vec::new();
+            for (mime_type, data) in contents {
+                formats.push((intern(&conn, mime_type)?, data.clone()));
+            }
+            // Plain text under the names older X11 clients ask for
+            if let Some((_, text)) = contents.iter().find(|(m, _)| m.starts_with("text/plain")) {
+                formats.push((intern(&conn, "UTF8_STRING")?, text.clone()));
+            }
+
+            conn.set_selection_owner(window, clipboard, CURRENT_TIME)
+                .map_err(|e| e.to_string())?;
+            let owner = conn
+                .get_selection_owner(clipboard)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?
+                .owner;
+            if owner != window {
+                return Err(fl!(
+                    "error-clipboard-unavailable",
+                    reason = "another application owns the selection"
+                ));
+            }
+
+            thread::Builder::new()
+                .name("clipboard-x11".to_string())
+                .spawn(move || serve(conn, clipboard, targets, formats))
+                .map_err(|e| e.to_string())?;
+            Ok(())
+        }
+
+        fn intern(conn: &RustConnection, name: &str) -> Result<Atom, String> {
+            Ok(conn
+                .intern_atom(false, name.as_bytes())
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?
+                .atom)
+        }
+
+        fn serve(
+            conn: RustConnection,
+            clipboard: Atom,
+            targets: Atom,
+            formats: Vec<(Atom, Vec<u8>)>,
+        ) {
+            loop {
+                match conn.wait_for_event() {
+                    Ok(Event::SelectionRequest(request)) if request.selection == clipboard => {
+                        if let Err(e) = answer(&conn, &request, targets, &formats) {
+                            tracing::warn!(error = %e, "Failed to answer clipboard request");
+                        }
+                    }
+                    // Someone else copied; our window and connection go away
+                    Ok(Event::SelectionClear(_)) | Err(_) => break,
+                    Ok(_) => {}
+                }
+            }
+        }
+
+        /// File lists are small, so everything is sent in one property
+        /// without the INCR protocol.
+        fn answer(
+            conn: &RustConnection,
+            request: &SelectionRequestEvent,
+            targets: Atom,
+            formats: &[(Atom, Vec<u8>)],
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            // Obsolete clients leave the property unset
+            let property = if request.property == NONE {
+                request.target
+            } else {
+                request.property
+            };
+
+            let answered = if request.target == targets {
+                let mut atoms = vec![targets];
+                atoms.extend(formats.iter().map(|(atom, _)| *atom));
+                conn.change_property32(
+                    PropMode::REPLACE,
+                    request.requestor,
+                    property,
+                    AtomEnum::ATOM,
+                    &atoms,
+                )?;
+                true
+            } else if let Some((atom, data)) =
+                formats.iter().find(|(atom, _)| *atom == request.target)
+            {
+                conn.change_property8(PropMode::REPLACE, request.requestor, property, *atom, data)?;
+                true
+            } else {
+                false
+            };
+
+            let notify = SelectionNotifyEvent {
+                response_type: SELECTION_NOTIFY_EVENT,
+                sequence: 0,
+                time: request.time,
+                requestor: request.requestor,
+                selection: request.selection,
+                target: request.target,
+                property: if answered {
+                    property
+                } else {
+                    AtomEnum::NONE.into()
+                },
+            };
+            conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify)?;
+            conn.flush()?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+mod platform {
+    use crate::i18n::fl;
+    use std::path::PathBuf;
+
+    pub fn copy_files(_paths: &[PathBuf]) -> Result<(), String> {
+        Err(fl!("error-not-supported"))
+    }
+}
@@ -0,0 +1,300 @@
+use crate::cache::CacheStats;
+use crate::models::{AppData, VersionRecord, CURRENT_VERSION};
+use crate::settings::Settings;
+use crate::thumbnail::pdf::PdfiumStatus;
+use aho_corasick::{AhoCorasick, BuildError};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Everything in `report.json` of a diagnostics bundle. File names and paths
+/// never appear in the library statistics; elsewhere they are replaced when
+/// redaction is on.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub generated_at: String,
+    pub app: AppInfo,
+    pub platform: PlatformInfo,
+    pub library: LibraryStats,
+    pub thumbnail_cache: CacheStats,
+    pub pdfium: PdfiumStatus,
+    pub settings: Settings,
+    pub redacted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AppInfo {
+    pub version: &'static str,
+    pub schema_version: u32,
+    pub library_schema_version: u32,
+    pub debug_build: bool,
+    pub language: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformInfo {
+    pub os: &'static str,
+    pub family: &'static str,
+    pub arch: &'static str,
+    /// `XDG_CURRENT_DESKTOP` and `XDG_SESSION_TYPE` on Linux
+    pub desktop: Option<String>,
+    pub session_type: Option<String>,
+}
+
+impl PlatformInfo {
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS,
+            family: std::env::consts::FAMILY,
+            arch: std::env::consts::ARCH,
+            desktop: std::env::var("XDG_CURRENT_DESKTOP").ok(),
+            session_type: std::env::var("XDG_SESSION_TYPE").ok(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct ExtensionStats {
+    pub count: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LibraryStats {
+    pub file_count: usize,
+    pub folder_count: usize,
+    pub total_bytes: u64,
+    pub tagged_file_count: usize,
+    pub tag_count: usize,
+    pub nested_tag_count: usize,
+    pub group_count: usize,
+    /// Files whose path no longer exists
+    pub missing_file_count: usize,
+    pub with_placeholder_count: usize,
+    pub by_extension: BTreeMap<String, ExtensionStats>,
+    pub version_history: Vec<VersionRecord>,
+}
+
+impl LibraryStats {
+    pub fn from_data(data: &AppData) -> Self {
+        let mut by_extension: BTreeMap<String, ExtensionStats> = BTreeMap::new();
+        for file in &data.files {
+            let stats = by_extension.entry(file.extension.clone()).or_default();
+            stats.count += 1;
+            stats.bytes += file.size;
+        }
+        Self {
+            file_count: data.files.len(),
+            folder_count: data
+                .files
+                .iter()
+                .filter(|f| f.mime_type == "inode/directory")
+                .count(),
+            total_bytes: data.files.iter().map(|f| f.size).sum(),
+            tagged_file_count: data.files.iter().filter(|f| !f.tag_ids.is_empty()).count(),
+            tag_count: data.tags.len(),
+            nested_tag_count: data.tags.iter().filter(|t| t.parent_id.is_some()).count(),
+            group_count: data.groups.len(),
+            missing_file_count: data
+                .files
+                .iter()
+                .filter(|f| !Path::new(&f.path).exists())
+                .count(),
+            with_placeholder_count: data
+                .files
+                .iter()
+                .filter(|f| f.placeholder.is_some())
+                .count(),
+            by_extension,
+            version_history: data.version_history.clone(),
+        }
+    }
+}
+
+impl AppInfo {
+    pub fn current(data: &AppData, language: String) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            schema_version: CURRENT_VERSION,
+            library_schema_version: data.version,
+            debug_build: cfg!(debug_assertions),
+            language,
+        }
+    }
+}
+
+/// Replaces known paths in text with placeholders: library files become
+/// `<file-N>`, their folders `<folder-N>` and the home directory `~`. Only
+/// whole paths are replaced, so `/photos/a` leaves `/photos/ab.jpg` alone.
+pub struct Redactor {
+    paths: AhoCorasick,
+    /// By pattern index of `paths`
+    placeholders: Vec<String>,
+}
+
+impl Redactor {
+    pub fn new(data: &AppData) -> Result<Self, BuildError> {
+        let mut replacements = Vec::new();
+        let mut folders: HashSet<PathBuf> = HashSet::new();
+        for (i, file) in data.files.iter().enumerate() {
+            replacements.push((file.path.clone(), format!("<file-{}>", i + 1)));
+            if let Some(parent) = Path::new(&file.path).parent() {
+                folders.insert(parent.to_path_buf());
+            }
+        }
+        let mut folders: Vec<PathBuf> = folders.into_iter().collect();
+        folders.sort();
+        for (i, folder) in folders.iter().enumerate() {
+            let folder = folder.to_string_lossy().to_string();
+            if !folder.is_empty() && folder != "/" {
+                replacements.push((folder, format!("<folder-{}>", i + 1)));
+            }
+        }
+        if let Some(home) = dirs::home_dir() {
+            replacements.push((home.to_string_lossy().to_string(), "~".to_string()));
+        }
+
+        // Paths also appear JSON-escaped in the report and the log files
+        let escaped: Vec<(String, String)> = replacements
+            .iter()
+            .filter(|(path, _)| path.contains('\\') || path.contains('"'))
+            .map(|(path, placeholder)| {
+                let json = serde_json::to_string(path).unwrap_or_default();
+                (json.trim_matches('"').to_string(), placeholder.clone())
+            })
+            .collect();
+        replacements.extend(escaped);
+        replacements.retain(|(path, _)| !path.is_empty());
+
+        let (patterns, placeholders): (Vec<String>, Vec<String>) = replacements.into_iter().unzip();
+        Ok(Self {
+            paths: AhoCorasick::new(&patterns)?,
+            placeholders,
+        })
+    }
+
+    /// Replace every known path in one pass over `text`. Where paths
+    /// overlap, the longest wins, so a file goes before its folder.
+    pub fn redact(&self, text: &str) -> String {
+        let mut matches: Vec<(usize, usize, usize)> = self
+            .paths
+            .find_overlapping_iter(text)
+            .filter(|m| is_whole_path(text, m.start(), m.end()))
+            .map(|m| (m.start(), m.end(), m.pattern().as_usize()))
+            .collect();
+        matches.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, pattern) in matches {
+            if start < last {
+                continue;
+            }
+            redacted.push_str(&text[last..start]);
+            redacted.push_str(&self.placeholders[pattern]);
+            last = end;
+        }
+        redacted.push_str(&text[last..]);
+        redacted
+    }
+}
+
+/// Whether `text[start..end]` is a path of its own rather than part of a
+/// longer name. A dot after it only continues the name when more of the
+/// name follows, so a path ending a sentence still counts.
+fn is_whole_path(text: &str, start: usize, end: usize) -> bool {
+    let in_name = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-');
+    if text[..start]
+        .chars()
+        .next_back()
+        .is_some_and(|c| in_name(c) || c == '.')
+    {
+        return false;
+    }
+    let mut after = text[end..].chars();
+    match after.next() {
+        Some('.') => !after.next().is_some_and(in_name),
+        Some(c) => !in_name(c),
+        None => true,
+    }
+}
+
+/// Write the bundle: `report.json` plus the log files under `logs/`.
+pub fn write_bundle(
+    destination: &Path,
+    report: &Report,
+    log_files: &[PathBuf],
+    redactor: Option<&Redactor>,
+) -> io::Result<()> {
+    let redact = |text: String| match redactor {
+        Some(redactor) => redactor.redact(&text),
+        None => text,
+    };
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(File::create(destination)?);
+
+    zip.start_file("report.json", options)?;
+    let report = serde_json::to_string_pretty(report).map_err(io::Error::other)?;
+    zip.write_all(redact(report).as_bytes())?;
+
+    for log_file in log_files {
+        let Some(name) = log_file.file_name() else {
+            continue;
+        };
+        // Logs are UTF-8 JSON lines; anything else is copied through lossily
+        let content = String::from_utf8_lossy(&fs::read(log_file)?).to_string();
+        zip.start_file(format!("logs/{}", name.to_string_lossy()), options)?;
+        zip.write_all(redact(content).as_bytes())?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::FileItem;
+
+    fn file(path: &str) -> FileItem {
+        FileItem {
+            id: path.to_string(),
+            name: String::new(),
+            path: path.to_string(),
+            extension: String::new(),
+            size: 0,
+            mime_type: String::new(),
+            added_at: 0,
+            tag_ids: Vec::new(),
+            metadata: Default::default(),
+            placeholder: None,
+        }
+    }
+
+    #[test]
+    fn redacts_whole_paths_only() {
+        let data = AppData {
+            files: vec![file("/photos/a"), file("/photos/b.jpg")],
+            ..AppData::default()
+        };
+        let redactor = Redactor::new(&data).unwrap();
+
+        assert_eq!(
+            redactor.redact("open /photos/b.jpg, then /photos/a."),
+            "open <file-2>, then <file-1>."
+        );
+        assert_eq!(
+            redactor.redact("/photos/ab.jpg and /photos/a.png"),
+            "<folder-1>/ab.jpg and <folder-1>/a.png"
+        );
+        assert_eq!(redactor.redact("/more/photos/a"), "/more/photos/a");
+        assert_eq!(
+            redactor.redact("\"path\":\"/photos/a\""),
+            "\"path\":\"<file-1>\""
+        );
+    }
+}
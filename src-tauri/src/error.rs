@@ -0,0 +1,201 @@
+use crate::i18n::fl;
+use crate::thumbnail::queue::JobError;
+use serde::ser::Error as _;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+/// Error returned by every command. Serialized as an object whose `code` is
+/// stable across releases plus the fields of the variant, so the frontend can
+/// react to specific failures, and the localized `message`:
+///
+/// `{ "code": "duplicate_tag_name", "name": "Holiday", "message": "..." }`
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "code", rename_all = "snake_case", remote = "Self")]
+pub enum InchBoxError {
+    /// No library file with this id
+    FileNotFound {
+        file_id: String,
+    },
+    /// Some of the library files to act on don't exist; nothing was changed
+    FilesNotFound {
+        file_ids: Vec<String>,
+    },
+    /// A path on disk doesn't exist or can't be read
+    PathNotFound {
+        path: String,
+    },
+    TagNotFound {
+        tag_id: String,
+    },
+    GroupNotFound {
+        group_id: String,
+    },
+    /// Tag names are unique, ignoring ASCII case
+    DuplicateTagName {
+        name: String,
+    },
+    /// The parent is the tag itself or one of its descendants
+    InvalidTagParent {
+        tag_id: String,
+        parent_id: String,
+    },
+    TagAlreadyAttached {
+        file_id: String,
+        tag_id: String,
+    },
+    TagNotAttached {
+        file_id: String,
+        tag_id: String,
+    },
+    InvalidColor {
+        color: String,
+    },
+    PageOutOfRange {
+        page: u16,
+        page_count: u16,
+    },
+    PdfiumUnavailable {
+        message: String,
+    },
+    ApplicationNotFound {
+        app_id: String,
+    },
+    /// No translation for this language
+    UnsupportedLanguage {
+        language: String,
+    },
+    /// Not a library archive, or a damaged one
+    InvalidArchive {
+        message: String,
+    },
+    /// The archive was written by a newer InchBox
+    UnsupportedArchiveVersion {
+        version: String,
+    },
+    /// Not a column the file listing export knows
+    UnknownColumn {
+        column: String,
+    },
+    /// The job was cancelled before it ran
+    Cancelled,
+    Thumbnail {
+        message: String,
+    },
+    /// Opening, revealing or copying through the desktop failed
+    Platform {
+        message: String,
+    },
+    Io {
+        message: String,
+    },
+}
+
+impl InchBoxError {
+    pub fn thumbnail(message: impl Into<String>) -> Self {
+        Self::Thumbnail {
+            message: message.into(),
+        }
+    }
+
+    pub fn platform(message: impl Into<String>) -> Self {
+        Self::Platform {
+            message: message.into(),
+        }
+    }
+}
+
+/// Localized in the current language; the frontend receives the same text
+/// as `message`.
+impl fmt::Display for InchBoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::FileNotFound { .. } => fl!("error-file-not-found"),
+            Self::FilesNotFound { file_ids } => {
+                fl!("error-files-not-found", count = file_ids.len())
+            }
+            Self::PathNotFound { path } => fl!("error-path-not-found", path = path.as_str()),
+            Self::TagNotFound { .. } => fl!("error-tag-not-found"),
+            Self::GroupNotFound { .. } => fl!("error-group-not-found"),
+            Self::DuplicateTagName { name } => {
+                fl!("error-duplicate-tag-name", name = name.as_str())
+            }
+            Self::InvalidTagParent { .. } => fl!("error-invalid-tag-parent"),
+            Self::TagAlreadyAttached { .. } => fl!("error-tag-already-attached"),
+            Self::TagNotAttached { .. } => fl!("error-tag-not-attached"),
+            Self::InvalidColor { color } => fl!("error-invalid-color", color = color.as_str()),
+            Self::PageOutOfRange { page, page_count } => fl!(
+                "error-page-out-of-range",
+                page = *page as u32 + 1,
+                page_count = *page_count
+            ),
+            Self::PdfiumUnavailable { message } => {
+                fl!("error-pdfium-unavailable", reason = message.as_str())
+            }
+            Self::ApplicationNotFound { app_id } => {
+                fl!("error-application-not-found", app_id = app_id.as_str())
+            }
+            Self::UnsupportedLanguage { language } => {
+                fl!("error-unsupported-language", language = language.as_str())
+            }
+            Self::InvalidArchive { message } => {
+                fl!("error-invalid-archive", reason = message.as_str())
+            }
+            Self::UnsupportedArchiveVersion { version } => {
+                fl!(
+                    "error-unsupported-archive-version",
+                    version = version.as_str()
+                )
+            }
+            Self::UnknownColumn { column } => {
+                fl!("error-unknown-column", column = column.as_str())
+            }
+            Self::Cancelled => fl!("error-cancelled"),
+            Self::Thumbnail { message } => {
+                fl!("error-thumbnail", reason = message.as_str())
+            }
+            Self::Platform { message } | Self::Io { message } => message.clone(),
+        };
+        f.write_str(&message)
+    }
+}
+
+impl Serialize for InchBoxError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        // The derived (remote = "Self") impl gives code and fields
+        let mut value = InchBoxError::serialize(self, serde_json::value::Serializer)
+            .map_err(S::Error::custom)?;
+        if let Value::Object(fields) = &mut value {
+            fields.insert("message".to_string(), Value::String(self.to_string()));
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl std::error::Error for InchBoxError {}
+
+impl From<std::io::Error> for InchBoxError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io {
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<JobError> for InchBoxError {
+    fn from(e: JobError) -> Self {
+        match e {
+            JobError::Cancelled => Self::Cancelled,
+            JobError::Failed(message) => Self::Thumbnail { message },
+        }
+    }
+}
+
+/// The queue dropped the job without running it.
+impl From<tokio::sync::oneshot::error::RecvError> for InchBoxError {
+    fn from(_: tokio::sync::oneshot::error::RecvError) -> Self {
+        Self::Cancelled
+    }
+}
+
+pub type Result<T> = std::result::Result<T, InchBoxError>;
@@ -0,0 +1,109 @@
+use crate::thumbnail;
+use image::DynamicImage;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Fields produced by an extractor, stored on `FileItem::metadata`.
+pub type MetadataFields = HashMap<String, Value>;
+
+/// A pluggable handler for a family of file formats.
+///
+/// Implement this to teach InchBox about a new format: `extract` feeds the
+/// metadata stored at import time, and `thumbnail` (optional) feeds the
+/// thumbnail pipeline. Register implementations on an `ExtractorRegistry`
+/// and pass it to `run_with_extractors`.
+pub trait MetadataExtractor: Send + Sync {
+    /// Short identifier used in error messages.
+    fn name(&self) -> &'static str;
+
+    /// Whether this extractor handles the given MIME type or (lowercase) extension.
+    fn supports(&self, mime_type: &str, extension: &str) -> bool;
+
+    /// Read metadata fields from the file.
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String>;
+
+    /// Produce a preview image no larger than `max_size` on either side.
+    /// Return `Ok(None)` when this extractor does not render previews, so the
+    /// next matching extractor is tried.
+    fn thumbnail(&self, _path: &Path, _max_size: u32) -> Result<Option<DynamicImage>, String> {
+        Ok(None)
+    }
+}
+
+/// Ordered set of extractors. Later registrations take priority, so custom
+/// extractors override the built-in ones for the formats they support.
+#[derive(Clone, Default)]
+pub struct ExtractorRegistry {
+    extractors: Vec<Arc<dyn MetadataExtractor>>,
+}
+
+impl ExtractorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry pre-populated with the extractors shipped with InchBox.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        // Registered first so every other provider takes precedence over it
+        #[cfg(all(target_os = "windows", feature = "shell-thumbnails"))]
+        registry.register(thumbnail::shell::ShellExtractor);
+        registry.register(thumbnail::raster::ImageExtractor);
+        registry.register(thumbnail::svg::SvgExtractor);
+        registry.register(thumbnail::raw::RawExtractor);
+        registry.register(thumbnail::pdf::PdfExtractor);
+        registry.register(thumbnail::video::VideoExtractor);
+        registry.register(thumbnail::audio::AudioExtractor);
+        registry.register(thumbnail::document::EpubExtractor);
+        registry.register(thumbnail::document::OfficeExtractor);
+        registry.register(thumbnail::document::TextExtractor);
+        registry
+    }
+
+    pub fn register<E: MetadataExtractor + 'static>(&mut self, extractor: E) {
+        self.extractors.push(Arc::new(extractor));
+    }
+
+    /// Extractors supporting the given type, highest priority first.
+    pub fn matching<'a>(
+        &'a self,
+        mime_type: &'a str,
+        extension: &'a str,
+    ) -> impl Iterator<Item = &'a dyn MetadataExtractor> + 'a {
+        self.extractors
+            .iter()
+            .rev()
+            .map(|e| e.as_ref())
+            .filter(move |e| e.supports(mime_type, extension))
+    }
+
+    /// Whether any extractor handles the type, so the backend can be asked
+    /// for its thumbnail.
+    pub fn can_thumbnail(&self, mime_type: &str, extension: &str) -> bool {
+        self.matching(mime_type, extension).next().is_some()
+    }
+
+    /// Run every matching extractor and merge the results. When two
+    /// extractors produce the same field, the higher priority one wins.
+    pub fn extract_all(&self, path: &Path, mime_type: &str, extension: &str) -> MetadataFields {
+        let mut fields = MetadataFields::new();
+        for extractor in self.matching(mime_type, extension) {
+            match extractor.extract(path) {
+                Ok(extracted) => {
+                    for (key, value) in extracted {
+                        fields.entry(key).or_insert(value);
+                    }
+                }
+                Err(e) => tracing::warn!(
+                    extractor = extractor.name(),
+                    path = %path.display(),
+                    error = %e,
+                    "Metadata extraction failed"
+                ),
+            }
+        }
+        fields
+    }
+}
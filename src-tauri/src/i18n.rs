@@ -0,0 +1,67 @@
+use crate::error::InchBoxError;
+use i18n_embed::{
+    fluent::FluentLanguageLoader, DesktopLanguageRequester, LanguageLoader, LanguageRequester,
+};
+use once_cell::sync::Lazy;
+use rust_embed::RustEmbed;
+use unic_langid::LanguageIdentifier;
+
+/// Fluent bundles in `i18n/<locale>/inchbox.ftl`. `build.rs` fails the build
+/// when a locale is missing a key another one has.
+#[derive(RustEmbed)]
+#[folder = "i18n/"]
+struct Localizations;
+
+pub static LANGUAGE_LOADER: Lazy<FluentLanguageLoader> = Lazy::new(|| {
+    let loader = i18n_embed::fluent::fluent_language_loader!();
+    // Bidi isolation marks around arguments would end up inside paths
+    loader.set_use_isolating(false);
+    let requested_languages = DesktopLanguageRequester::new().requested_languages();
+    let _ = i18n_embed::select(&loader, &Localizations, &requested_languages);
+    loader
+});
+
+/// Switch the backend language to `language` (`zh-CN`, `zh`, `en`...), or
+/// back to the desktop locale with `None`. Returns the locale now in use.
+pub fn select(language: Option<&str>) -> Result<String, InchBoxError> {
+    let unsupported = || InchBoxError::UnsupportedLanguage {
+        language: language.unwrap_or_default().to_string(),
+    };
+    let requested: Vec<LanguageIdentifier> = match language {
+        Some(language) => vec![language.parse().map_err(|_| unsupported())?],
+        None => DesktopLanguageRequester::new().requested_languages(),
+    };
+
+    // Negotiation would quietly fall back to English for unknown languages
+    if language.is_some() {
+        let available = LANGUAGE_LOADER
+            .available_languages(&Localizations)
+            .map_err(|e| InchBoxError::platform(e.to_string()))?;
+        if !available
+            .iter()
+            .any(|l| l.language == requested[0].language)
+        {
+            return Err(unsupported());
+        }
+    }
+
+    i18n_embed::select(&*LANGUAGE_LOADER, &Localizations, &requested)
+        .map_err(|e| InchBoxError::platform(e.to_string()))?;
+    Ok(current_language())
+}
+
+pub fn current_language() -> String {
+    LANGUAGE_LOADER.current_language().to_string()
+}
+
+/// Localized text for a message id, checked against the fallback locale at
+/// compile time: `fl!("error-path-not-found", path = path)`.
+macro_rules! fl {
+    ($message_id:literal) => {{
+        i18n_embed_fl::fl!($crate::i18n::LANGUAGE_LOADER, $message_id)
+    }};
+    ($message_id:literal, $($args:expr),*) => {{
+        i18n_embed_fl::fl!($crate::i18n::LANGUAGE_LOADER, $message_id, $($args),*)
+    }};
+}
+pub(crate) use fl;
@@ -1,54 +1,204 @@
+mod archive;
 mod cache;
+mod clipboard;
+mod diagnostics;
+mod error;
+pub mod extractor;
+mod i18n;
+mod listing;
+mod logging;
+mod mirror;
 mod models;
-#[cfg(target_os = "windows")]
+mod open_with;
+mod reveal;
+mod settings;
 mod thumbnail;
+mod xdg_tags;
+mod xmp;
 
+use cache::{CacheStats, ThumbnailCacheState};
 use chrono::Utc;
+use error::InchBoxError;
+use extractor::ExtractorRegistry;
+use i18n::fl;
 use mime_guess::from_path;
-use models::{AppData, AppState, FileItem, Tag, TagGroup};
+use mirror::TagMirror;
+use models::{AppData, AppState, FileItem, Tag, TagGroup, TagPathConflict};
 use serde::Serialize;
-use std::collections::HashSet;
+use settings::{Settings, SettingsPatch, SettingsState};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
-use std::process::Command;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::path::{Path, PathBuf};
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
     Emitter, Manager, State,
 };
 use tauri_plugin_fs::FsExt;
+use thumbnail::placeholder::Placeholder;
+use thumbnail::queue::{JobError, Priority, ThumbnailQueue, MAX_CONCURRENT_THUMBNAILS};
+use thumbnail::{ThumbnailFormat, ThumbnailSize};
 use uuid::Uuid;
 
-// Global atomic counter for thumbnail generation (max 4 concurrent)
-static THUMBNAIL_COUNTER: AtomicUsize = AtomicUsize::new(0);
-const MAX_CONCURRENT_THUMBNAILS: usize = 4;
+// Helper to save data without re-locking
+fn save_to_disk(data: &AppData, file_path: &Path) {
+    if let Ok(content) = serde_json::to_string_pretty(data) {
+        if let Err(e) = fs::write(file_path, content) {
+            tracing::error!(path = %file_path.display(), error = %e, "Failed to save library");
+        }
+    }
+}
 
-// i18n
-use i18n_embed::{fluent::FluentLanguageLoader, DesktopLanguageRequester, LanguageRequester};
-use once_cell::sync::Lazy;
-use rust_embed::RustEmbed;
+fn file_path_of(data: &AppData, file_id: &str) -> error::Result<String> {
+    data.files
+        .iter()
+        .find(|f| f.id == file_id)
+        .map(|f| f.path.clone())
+        .ok_or_else(|| InchBoxError::FileNotFound {
+            file_id: file_id.to_string(),
+        })
+}
 
-#[derive(RustEmbed)]
-#[folder = "i18n/"]
-struct Localizations;
+fn ensure_tag(data: &AppData, tag_id: &str) -> error::Result<()> {
+    if data.tags.iter().any(|t| t.id == tag_id) {
+        Ok(())
+    } else {
+        Err(InchBoxError::TagNotFound {
+            tag_id: tag_id.to_string(),
+        })
+    }
+}
 
-static LANGUAGE_LOADER: Lazy<FluentLanguageLoader> = Lazy::new(|| {
-    let loader = i18n_embed::fluent::fluent_language_loader!();
-    let requested_languages = DesktopLanguageRequester::new().requested_languages();
-    let _ = i18n_embed::select(&loader, &Localizations, &requested_languages);
-    loader
-});
+fn ensure_group(data: &AppData, group_id: &str) -> error::Result<()> {
+    if data.groups.iter().any(|g| g.id == group_id) {
+        Ok(())
+    } else {
+        Err(InchBoxError::GroupNotFound {
+            group_id: group_id.to_string(),
+        })
+    }
+}
 
-fn get_localized_string(key: &str) -> String {
-    LANGUAGE_LOADER.get(key)
+/// Size and modification time (seconds) of a file on disk, used to
+/// validate cached thumbnails.
+fn stat(path: &str) -> error::Result<(u64, i64)> {
+    let metadata = std::fs::metadata(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => InchBoxError::PathNotFound {
+            path: path.to_string(),
+        },
+        _ => e.into(),
+    })?;
+    let modified_time = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    Ok((metadata.len(), modified_time))
 }
 
-// Helper to save data without re-locking
-fn save_to_disk(data: &AppData, file_path: &Path) {
-    if let Ok(content) = serde_json::to_string_pretty(data) {
-        let _ = fs::write(file_path, content);
+/// Run disk-bound work on the blocking thread pool, off the async runtime
+/// and the main thread.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+) -> error::Result<T> {
+    tauri::async_runtime::spawn_blocking(work)
+        .await
+        .map_err(|e| InchBoxError::Io {
+            message: e.to_string(),
+        })
+}
+
+/// Files tagged with `tag_id` or one of its descendants, whose tag paths
+/// change when the tag is renamed, moved or deleted.
+fn files_under_tag(data: &AppData, tag_id: &str) -> HashSet<String> {
+    let mut subtree: HashSet<&str> = HashSet::from([tag_id]);
+    loop {
+        let before = subtree.len();
+        for tag in &data.tags {
+            if tag
+                .parent_id
+                .as_deref()
+                .is_some_and(|parent| subtree.contains(parent))
+            {
+                subtree.insert(&tag.id);
+            }
+        }
+        if subtree.len() == before {
+            break;
+        }
+    }
+    data.files
+        .iter()
+        .filter(|f| f.tag_ids.iter().any(|t| subtree.contains(t.as_str())))
+        .map(|f| f.id.clone())
+        .collect()
+}
+
+/// Tray menu entries, relabelled when the language changes.
+struct TrayMenu {
+    show: MenuItem<tauri::Wry>,
+    quit: MenuItem<tauri::Wry>,
+}
+
+impl TrayMenu {
+    fn relabel(&self) {
+        for (item, text) in [
+            (&self.show, fl!("tray-menu-show")),
+            (&self.quit, fl!("tray-menu-quit")),
+        ] {
+            if let Err(e) = item.set_text(text) {
+                tracing::warn!(error = %e, "Failed to relabel tray menu");
+            }
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct LanguageChangedEvent {
+    /// Requested language, `None` when following the desktop
+    requested: Option<String>,
+    /// Locale of the backend translations now in use, e.g. `zh-CN`
+    language: String,
+}
+
+#[derive(Clone, Serialize)]
+struct PlaceholderEvent {
+    file_id: String,
+    placeholder: Placeholder,
+}
+
+/// Remember the placeholder derived from a fresh thumbnail. The UI is only
+/// told when it actually changed; the library is saved in batches, as
+/// nearly every first thumbnail brings one.
+fn store_placeholder(app: &tauri::AppHandle, file_id: &str, img: &image::DynamicImage) {
+    let placeholder = match thumbnail::placeholder::compute(img) {
+        Ok(placeholder) => placeholder,
+        Err(e) => {
+            tracing::warn!(file_id, error = %e, "Failed to compute placeholder");
+            return;
+        }
+    };
+
+    let state = app.state::<AppState>();
+    {
+        let mut data = state.data.lock().unwrap();
+        let Some(file) = data.files.iter_mut().find(|f| f.id == file_id) else {
+            return;
+        };
+        if file.placeholder.as_ref() == Some(&placeholder) {
+            return;
+        }
+        file.placeholder = Some(placeholder.clone());
     }
+    state.mark_dirty();
+
+    let _ = app.emit(
+        "file-placeholder",
+        PlaceholderEvent {
+            file_id: file_id.to_string(),
+            placeholder,
+        },
+    );
 }
 
 #[tauri::command]
@@ -57,129 +207,327 @@ fn get_initial_data(state: State<AppState>) -> AppData {
     data.clone()
 }
 
+#[tauri::command]
+fn get_settings(settings: State<SettingsState>) -> Settings {
+    settings.get()
+}
+
+/// Change the settings given in `patch` and keep the others as they are.
+#[tauri::command]
+fn update_settings(
+    patch: SettingsPatch,
+    settings: State<SettingsState>,
+    thumb_cache: State<ThumbnailCacheState>,
+) -> Settings {
+    let mut current = settings.data.lock().unwrap();
+    patch.apply(&mut current);
+    settings.save(&current);
+    thumb_cache
+        .cache
+        .lock()
+        .unwrap()
+        .set_max_bytes(current.max_thumbnail_cache_bytes());
+    current.clone()
+}
+
+/// Switch the backend language and remember it. `None` follows the desktop
+/// locale again.
+#[tauri::command]
+fn set_language(
+    language: Option<String>,
+    app: tauri::AppHandle,
+    settings: State<SettingsState>,
+) -> error::Result<String> {
+    let selected = i18n::select(language.as_deref())?;
+    {
+        let mut current = settings.data.lock().unwrap();
+        current.language = language.clone();
+        settings.save(&current);
+    }
+    if let Some(tray) = app.try_state::<TrayMenu>() {
+        tray.relabel();
+    }
+    let _ = app.emit(
+        "language-changed",
+        LanguageChangedEvent {
+            requested: language,
+            language: selected.clone(),
+        },
+    );
+    Ok(selected)
+}
+
 #[derive(Serialize)]
 pub struct AddFilesResponse {
     pub added_files: Vec<FileItem>,
     pub skipped_duplicates: Vec<String>,
+    /// Tags created for keywords or attributes found in the added files
+    pub created_tags: Vec<Tag>,
+    /// Keywords or attributes left out, their names sit elsewhere among the tags
+    pub tag_conflicts: Vec<TagPathConflict>,
+    /// Summary of the above in the current language
+    pub message: String,
+}
+
+impl AddFilesResponse {
+    fn new(
+        added_files: Vec<FileItem>,
+        skipped_duplicates: Vec<String>,
+        created_tags: Vec<Tag>,
+        tag_conflicts: Vec<TagPathConflict>,
+    ) -> Self {
+        let mut parts = vec![if added_files.is_empty() {
+            fl!("add-files-none")
+        } else {
+            fl!("add-files-added", count = added_files.len())
+        }];
+        if !skipped_duplicates.is_empty() {
+            parts.push(fl!(
+                "add-files-skipped-duplicates",
+                count = skipped_duplicates.len()
+            ));
+        }
+        if !tag_conflicts.is_empty() {
+            parts.push(fl!("add-files-tag-conflicts", count = tag_conflicts.len()));
+        }
+        Self {
+            added_files,
+            skipped_duplicates,
+            created_tags,
+            tag_conflicts,
+            message: parts.join(" "),
+        }
+    }
 }
 
+/// A file read from disk by `add_files`, before it goes into the library
+struct NewFile {
+    item: FileItem,
+    /// Tags other applications stored with the file, as paths root first
+    tag_paths: Vec<Vec<String>>,
+}
+
+/// Stat the file, extract its metadata and read the tags other
+/// applications stored with it. `None` when it doesn't exist.
+fn read_new_file(
+    path_str: String,
+    registry: &ExtractorRegistry,
+    settings: &Settings,
+) -> Option<NewFile> {
+    let path = Path::new(&path_str);
+    if !path.exists() {
+        return None;
+    }
+
+    let is_dir = path.is_dir();
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let size = if is_dir {
+        0
+    } else {
+        path.metadata().map(|m| m.len()).unwrap_or(0)
+    };
+    let mime_type = if is_dir {
+        "inode/directory".to_string()
+    } else {
+        from_path(path).first_or_octet_stream().to_string()
+    };
+
+    let metadata = if is_dir {
+        Default::default()
+    } else {
+        registry.extract_all(path, &mime_type, &extension)
+    };
+
+    let final_extension = if is_dir {
+        "folder".to_string()
+    } else {
+        extension
+    };
+
+    // Tags other applications stored with the file become its tags
+    let mut tag_paths: Vec<Vec<String>> = Vec::new();
+    if settings.xmp_sync && !is_dir {
+        match xmp::read(path, &mime_type) {
+            Ok(keywords) => tag_paths.extend(keywords.tag_paths()),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to read XMP keywords")
+            }
+        }
+    }
+    if settings.xattr_tags {
+        match xdg_tags::read(path) {
+            Ok(paths) => tag_paths.extend(paths),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to read tag attribute")
+            }
+        }
+    }
+
+    let item = FileItem {
+        id: Uuid::new_v4().to_string(),
+        name,
+        path: path_str,
+        extension: final_extension,
+        size,
+        mime_type,
+        added_at: Utc::now().timestamp_millis(),
+        tag_ids: Vec::new(),
+        metadata,
+        placeholder: None,
+    };
+    Some(NewFile { item, tag_paths })
+}
+
+/// Add files and folders to the library. Reading them (metadata, XMP,
+/// attributes) happens off the library lock, which is only taken to check
+/// for duplicates and to insert.
 #[tauri::command]
-fn add_files(
+async fn add_files(
     app: tauri::AppHandle,
     paths: Vec<String>,
-    state: State<AppState>,
-) -> AddFilesResponse {
-    let mut data = state.data.lock().unwrap();
+    state: State<'_, AppState>,
+    registry: State<'_, ExtractorRegistry>,
+    settings: State<'_, SettingsState>,
+) -> error::Result<AddFilesResponse> {
     let mut added_files = Vec::new();
     let mut skipped_duplicates = Vec::new();
+    let mut created_tags = Vec::new();
+    let mut tag_conflicts = Vec::new();
 
-    // Create a HashSet of existing paths for fast lookup
-    let existing_paths: HashSet<String> = data.files.iter().map(|f| f.path.clone()).collect();
+    // Paths not in the library yet, each once
+    let new_paths: Vec<String> = {
+        let data = state.data.lock().unwrap();
+        let mut known: HashSet<String> = data.files.iter().map(|f| f.path.clone()).collect();
+        paths
+            .into_iter()
+            .filter(|path| {
+                let new = known.insert(path.clone());
+                if !new {
+                    skipped_duplicates.push(path.clone());
+                }
+                new
+            })
+            .collect()
+    };
 
-    for path_str in paths {
-        // Check for duplicates
-        if existing_paths.contains(&path_str) {
-            skipped_duplicates.push(path_str);
-            continue;
-        }
+    let registry = registry.inner().clone();
+    let settings = settings.get();
+    let new_files = blocking(move || {
+        new_paths
+            .into_iter()
+            .filter_map(|path| read_new_file(path, &registry, &settings))
+            .collect::<Vec<_>>()
+    })
+    .await?;
 
-        let path = Path::new(&path_str);
-        if !path.exists() {
-            continue;
+    {
+        let mut data = state.data.lock().unwrap();
+        // Another call may have added some of them meanwhile
+        let existing_paths: HashSet<String> = data.files.iter().map(|f| f.path.clone()).collect();
+        for new_file in new_files {
+            let mut item = new_file.item;
+            if existing_paths.contains(&item.path) {
+                skipped_duplicates.push(item.path);
+                continue;
+            }
+            for tag_path in new_file.tag_paths {
+                match data.ensure_tag_path(&tag_path, &mut created_tags) {
+                    Ok(Some(id)) if !item.tag_ids.contains(&id) => item.tag_ids.push(id),
+                    Ok(_) => {}
+                    Err(conflict) => {
+                        tracing::warn!(
+                            path = %item.path,
+                            tag_path = ?conflict.path,
+                            existing = ?conflict.existing,
+                            "Tag name already used elsewhere"
+                        );
+                        tag_conflicts.push(conflict);
+                    }
+                }
+            }
+            added_files.push(item.clone());
+            data.files.push(item);
         }
+        if !added_files.is_empty() {
+            save_to_disk(&data, &state.file_path);
+        }
+    }
 
-        // Dynamically allow access to this path immediately
-        let scope = app.fs_scope();
-        let ret = if path.is_dir() {
+    // Dynamically allow access to the added paths
+    let scope = app.fs_scope();
+    for file in &added_files {
+        let path = Path::new(&file.path);
+        let ret = if file.mime_type == "inode/directory" {
             scope.allow_directory(path, true)
         } else {
             scope.allow_file(path)
         };
-
         if let Err(e) = ret {
-            // Log error to console/terminal for debugging
-            println!("Failed to extend fs scope for {:?}: {}", path, e);
+            tracing::warn!(path = %path.display(), error = %e, "Failed to extend fs scope");
         }
-
-        let is_dir = path.is_dir();
-        let extension = path
-            .extension()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_lowercase();
-
-        let name = path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        let size = if is_dir {
-            0
-        } else {
-            path.metadata().map(|m| m.len()).unwrap_or(0)
-        };
-        let mime_type = if is_dir {
-            "inode/directory".to_string()
-        } else {
-            from_path(path).first_or_octet_stream().to_string()
-        };
-
-        let final_extension = if is_dir {
-            "folder".to_string()
-        } else {
-            extension
-        };
-
-        let file_item = FileItem {
-            id: Uuid::new_v4().to_string(),
-            name,
-            path: path_str.clone(),
-            extension: final_extension,
-            size,
-            mime_type,
-            added_at: Utc::now().timestamp_millis(),
-            tag_ids: Vec::new(),
-        };
-        added_files.push(file_item.clone());
-        data.files.push(file_item);
     }
-    save_to_disk(&data, &state.file_path);
 
-    AddFilesResponse {
+    tracing::info!(
+        added = added_files.len(),
+        skipped_duplicates = skipped_duplicates.len(),
+        created_tags = created_tags.len(),
+        tag_conflicts = tag_conflicts.len(),
+        "Added files"
+    );
+
+    Ok(AddFilesResponse::new(
         added_files,
         skipped_duplicates,
-    }
+        created_tags,
+        tag_conflicts,
+    ))
 }
 
+/// Remove files from the library. Fails without removing anything when
+/// any of the ids is unknown.
 #[tauri::command]
-fn delete_files(ids: Vec<String>, state: State<AppState>, app: tauri::AppHandle) {
+fn delete_files(
+    ids: Vec<String>,
+    state: State<AppState>,
+    thumb_cache: State<ThumbnailCacheState>,
+) -> error::Result<()> {
     let mut data = state.data.lock().unwrap();
     let id_set: HashSet<_> = ids.clone().into_iter().collect();
-
-    // Clean up thumbnails for deleted files
-    let app_data_dir = app
-        .path()
-        .app_data_dir()
-        .unwrap_or_else(|_| std::path::PathBuf::from("."));
-    let thumb_dir = app_data_dir.join("thumbnails");
-    let cache_path = thumb_dir.clone();
-
-    // Remove thumbnails and update cache
-    for file_id in &ids {
-        let thumb_path = thumb_dir.join(format!("{}.jpg", file_id));
-        if thumb_path.exists() {
-            let _ = std::fs::remove_file(&thumb_path);
-        }
-
-        // Update cache metadata
-        if let Ok(mut cache) = cache::ThumbnailCache::new(&cache_path) {
-            cache.remove_metadata(file_id);
-            let _ = cache.save();
-        }
+    let known: HashSet<&String> = data.files.iter().map(|f| &f.id).collect();
+    let missing: Vec<String> = ids
+        .iter()
+        .filter(|id| !known.contains(id))
+        .cloned()
+        .collect();
+    if !missing.is_empty() {
+        return Err(InchBoxError::FilesNotFound { file_ids: missing });
     }
 
     data.files.retain(|f| !id_set.contains(&f.id));
     save_to_disk(&data, &state.file_path);
+    drop(data);
+
+    // Forget the thumbnails, then delete them without holding the cache
+    let thumbnails: Vec<PathBuf> = {
+        let mut cache = thumb_cache.cache.lock().unwrap();
+        ids.iter()
+            .flat_map(|file_id| cache.remove_file_entries(file_id))
+            .collect()
+    };
+    for thumb_path in thumbnails {
+        let _ = fs::remove_file(thumb_path);
+    }
+    Ok(())
 }
 
 // --- Tag Group Ops ---
@@ -203,22 +551,27 @@ fn update_tag_group(
     name: Option<String>,
     color: Option<String>,
     state: State<AppState>,
-) {
+) -> error::Result<()> {
     let mut data = state.data.lock().unwrap();
-    if let Some(group) = data.groups.iter_mut().find(|g| g.id == id) {
-        if let Some(n) = name {
-            group.name = n;
-        }
-        if let Some(c) = color {
-            group.color = Some(c);
-        }
-        save_to_disk(&data, &state.file_path);
+    let group = data
+        .groups
+        .iter_mut()
+        .find(|g| g.id == id)
+        .ok_or(InchBoxError::GroupNotFound { group_id: id })?;
+    if let Some(n) = name {
+        group.name = n;
+    }
+    if let Some(c) = color {
+        group.color = Some(c);
     }
+    save_to_disk(&data, &state.file_path);
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_tag_group(id: String, state: State<AppState>) {
+fn delete_tag_group(id: String, state: State<AppState>) -> error::Result<()> {
     let mut data = state.data.lock().unwrap();
+    ensure_group(&data, &id)?;
     // Ungroup tags
     for tag in data.tags.iter_mut() {
         if tag.group_id.as_ref() == Some(&id) {
@@ -228,6 +581,7 @@ fn delete_tag_group(id: String, state: State<AppState>) {
     // Delete group
     data.groups.retain(|g| g.id != id);
     save_to_disk(&data, &state.file_path);
+    Ok(())
 }
 
 // --- Tag Ops ---
@@ -238,12 +592,18 @@ fn create_tag(
     parent_id: Option<String>,
     group_id: Option<String>,
     state: State<AppState>,
-) -> Result<Tag, String> {
+) -> error::Result<Tag> {
     let mut data = state.data.lock().unwrap();
 
     // Check for duplicate tag name (case-insensitive)
     if data.tags.iter().any(|t| t.name.eq_ignore_ascii_case(&name)) {
-        return Err("Duplicate tag name".to_string());
+        return Err(InchBoxError::DuplicateTagName { name });
+    }
+    if let Some(parent_id) = &parent_id {
+        ensure_tag(&data, parent_id)?;
+    }
+    if let Some(group_id) = &group_id {
+        ensure_group(&data, group_id)?;
     }
 
     let tag = Tag {
@@ -258,7 +618,13 @@ fn create_tag(
 }
 
 #[tauri::command]
-fn rename_tag(id: String, name: String, state: State<AppState>) -> Result<(), String> {
+fn rename_tag(
+    id: String,
+    name: String,
+    state: State<AppState>,
+    settings: State<SettingsState>,
+    mirror: State<TagMirror>,
+) -> error::Result<()> {
     let mut data = state.data.lock().unwrap();
 
     // Check for duplicates (excluding self)
@@ -267,16 +633,20 @@ fn rename_tag(id: String, name: String, state: State<AppState>) -> Result<(), St
         .iter()
         .any(|t| t.id != id && t.name.eq_ignore_ascii_case(&name))
     {
-        return Err("Duplicate tag name".to_string());
+        return Err(InchBoxError::DuplicateTagName { name });
     }
+    let settings = settings.get();
+    let before = TagMirror::snapshot(&data, &settings, &files_under_tag(&data, &id));
 
-    if let Some(tag) = data.tags.iter_mut().find(|t| t.id == id) {
-        tag.name = name;
-        save_to_disk(&data, &state.file_path);
-        Ok(())
-    } else {
-        Err("Tag not found".to_string())
-    }
+    let tag = data
+        .tags
+        .iter_mut()
+        .find(|t| t.id == id)
+        .ok_or_else(|| InchBoxError::TagNotFound { tag_id: id.clone() })?;
+    tag.name = name;
+    save_to_disk(&data, &state.file_path);
+    mirror.submit(&data, &settings, before);
+    Ok(())
 }
 
 #[tauri::command]
@@ -285,18 +655,55 @@ fn move_tag(
     parent_id: Option<String>,
     group_id: Option<String>,
     state: State<AppState>,
-) {
+    settings: State<SettingsState>,
+    mirror: State<TagMirror>,
+) -> error::Result<()> {
     let mut data = state.data.lock().unwrap();
+    ensure_tag(&data, &id)?;
+    if let Some(group_id) = &group_id {
+        ensure_group(&data, group_id)?;
+    }
+    if let Some(parent_id) = &parent_id {
+        ensure_tag(&data, parent_id)?;
+        // Walk up from the new parent; reaching the tag would make a cycle
+        let mut ancestor = Some(parent_id.clone());
+        while let Some(current) = ancestor {
+            if current == id {
+                return Err(InchBoxError::InvalidTagParent {
+                    tag_id: id,
+                    parent_id: parent_id.clone(),
+                });
+            }
+            ancestor = data
+                .tags
+                .iter()
+                .find(|t| t.id == current)
+                .and_then(|t| t.parent_id.clone());
+        }
+    }
+
+    let settings = settings.get();
+    let before = TagMirror::snapshot(&data, &settings, &files_under_tag(&data, &id));
     if let Some(tag) = data.tags.iter_mut().find(|t| t.id == id) {
         tag.parent_id = parent_id;
         tag.group_id = group_id;
-        save_to_disk(&data, &state.file_path);
     }
+    save_to_disk(&data, &state.file_path);
+    mirror.submit(&data, &settings, before);
+    Ok(())
 }
 
 #[tauri::command]
-fn delete_tag(id: String, state: State<AppState>) {
+fn delete_tag(
+    id: String,
+    state: State<AppState>,
+    settings: State<SettingsState>,
+    mirror: State<TagMirror>,
+) -> error::Result<()> {
     let mut data = state.data.lock().unwrap();
+    ensure_tag(&data, &id)?;
+    let settings = settings.get();
+    let before = TagMirror::snapshot(&data, &settings, &files_under_tag(&data, &id));
     // Detach from files
     for file in data.files.iter_mut() {
         file.tag_ids.retain(|tid| tid != &id);
@@ -310,65 +717,148 @@ fn delete_tag(id: String, state: State<AppState>) {
     // Delete tag
     data.tags.retain(|t| t.id != id);
     save_to_disk(&data, &state.file_path);
+    mirror.submit(&data, &settings, before);
+    Ok(())
 }
 
 // --- File Tag Ops ---
 
 #[tauri::command]
-fn attach_tag(file_id: String, tag_id: String, state: State<AppState>) {
+fn attach_tag(
+    file_id: String,
+    tag_id: String,
+    state: State<AppState>,
+    settings: State<SettingsState>,
+    mirror: State<TagMirror>,
+) -> error::Result<()> {
     let mut data = state.data.lock().unwrap();
-    if let Some(file) = data.files.iter_mut().find(|f| f.id == file_id) {
-        if !file.tag_ids.contains(&tag_id) {
-            file.tag_ids.push(tag_id);
-            save_to_disk(&data, &state.file_path);
-        }
+    ensure_tag(&data, &tag_id)?;
+    let settings = settings.get();
+    let before = TagMirror::snapshot(&data, &settings, &HashSet::from([file_id.clone()]));
+    let file = data
+        .files
+        .iter_mut()
+        .find(|f| f.id == file_id)
+        .ok_or_else(|| InchBoxError::FileNotFound {
+            file_id: file_id.clone(),
+        })?;
+    if file.tag_ids.contains(&tag_id) {
+        return Err(InchBoxError::TagAlreadyAttached { file_id, tag_id });
     }
+    file.tag_ids.push(tag_id);
+    save_to_disk(&data, &state.file_path);
+    mirror.submit(&data, &settings, before);
+    Ok(())
 }
 
 #[tauri::command]
-fn detach_tag(file_id: String, tag_id: String, state: State<AppState>) {
+fn detach_tag(
+    file_id: String,
+    tag_id: String,
+    state: State<AppState>,
+    settings: State<SettingsState>,
+    mirror: State<TagMirror>,
+) -> error::Result<()> {
     let mut data = state.data.lock().unwrap();
-    if let Some(file) = data.files.iter_mut().find(|f| f.id == file_id) {
-        if let Some(pos) = file.tag_ids.iter().position(|t| t == &tag_id) {
-            file.tag_ids.remove(pos);
-            save_to_disk(&data, &state.file_path);
-        }
-    }
+    let settings = settings.get();
+    let before = TagMirror::snapshot(&data, &settings, &HashSet::from([file_id.clone()]));
+    let file = data
+        .files
+        .iter_mut()
+        .find(|f| f.id == file_id)
+        .ok_or_else(|| InchBoxError::FileNotFound {
+            file_id: file_id.clone(),
+        })?;
+    let pos = file
+        .tag_ids
+        .iter()
+        .position(|t| t == &tag_id)
+        .ok_or_else(|| InchBoxError::TagNotAttached {
+            file_id: file_id.clone(),
+            tag_id: tag_id.clone(),
+        })?;
+    file.tag_ids.remove(pos);
+    save_to_disk(&data, &state.file_path);
+    mirror.submit(&data, &settings, before);
+    Ok(())
 }
 
 // --- OS Ops ---
 
+/// Open with the application chosen for this extension, or the system
+/// default when there is none.
 #[tauri::command]
-fn open_file_default(path: String) -> Result<(), String> {
-    open::that(path).map_err(|e| e.to_string())
+fn open_file_default(path: String, settings: State<SettingsState>) -> error::Result<()> {
+    let path = std::path::PathBuf::from(path);
+    if let Some(app_id) = settings.get().preferred_app(&path) {
+        match open_with::open_with(&path, app_id) {
+            Ok(()) => return Ok(()),
+            // The application may have been uninstalled since
+            Err(e) => tracing::warn!(app_id, error = %e, "Preferred app failed, using default"),
+        }
+    }
+    open::that(path)
+        .map_err(|e| InchBoxError::platform(fl!("error-open-failed", reason = e.to_string())))
 }
 
+/// Applications able to open the file, for the "Open with" menu.
 #[tauri::command]
-fn show_in_explorer(path: String) -> Result<(), String> {
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("explorer")
-            .args(["/select,", &path])
-            .spawn()
-            .map_err(|e| e.to_string())?;
-        Ok(())
+fn get_open_with_apps(path: String, settings: State<SettingsState>) -> Vec<open_with::AppInfo> {
+    let path = std::path::PathBuf::from(path);
+    let mime_type = mime_guess::from_path(&path)
+        .first_or_octet_stream()
+        .to_string();
+    let preferred = settings.get().preferred_app(&path).cloned();
+
+    let mut apps = open_with::apps_for(&path, &mime_type);
+    for app in apps.iter_mut() {
+        app.is_preferred = preferred.as_ref() == Some(&app.id);
     }
-    #[cfg(not(target_os = "windows"))]
-    {
-        open::that(path).map_err(|e| e.to_string())
+    // Preferred first, then the system default; the sort is stable
+    apps.sort_by_key(|app| (!app.is_preferred, !app.is_default));
+    apps
+}
+
+/// Open the file with `app_id` from `get_open_with_apps`. With `remember`
+/// the app becomes the one `open_file_default` uses for the extension; an
+/// empty `app_id` goes back to the system default.
+#[tauri::command]
+fn open_file_with(
+    path: String,
+    app_id: String,
+    remember: bool,
+    settings: State<SettingsState>,
+) -> error::Result<()> {
+    let path = std::path::PathBuf::from(path);
+    if remember {
+        if let Some(extension) = settings::extension_key(&path) {
+            let mut current = settings.data.lock().unwrap();
+            if app_id.is_empty() {
+                current.preferred_apps.remove(&extension);
+            } else {
+                current.preferred_apps.insert(extension, app_id.clone());
+            }
+            settings.save(&current);
+        }
+    }
+    if app_id.is_empty() {
+        open::that(path)
+            .map_err(|e| InchBoxError::platform(fl!("error-open-failed", reason = e.to_string())))
+    } else {
+        open_with::open_with(&path, &app_id)
     }
 }
 
+/// Reveal the given files in the system file manager, selected.
 #[tauri::command]
-fn get_file_info(path: String) -> Result<serde_json::Value, String> {
-    let metadata = std::fs::metadata(&path).map_err(|e| e.to_string())?;
-    let size = metadata.len();
-    let modified_time = metadata
-        .modified()
-        .map_err(|e| e.to_string())?
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| format!("Invalid modified time: {}", e))?
-        .as_secs() as i64;
+async fn show_in_explorer(paths: Vec<String>) -> error::Result<()> {
+    let paths: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    reveal::reveal(paths).await.map_err(InchBoxError::platform)
+}
+
+#[tauri::command]
+fn get_file_info(path: String) -> error::Result<serde_json::Value> {
+    let (size, modified_time) = stat(&path)?;
 
     Ok(serde_json::json!({
         "size": size,
@@ -377,105 +867,777 @@ fn get_file_info(path: String) -> Result<serde_json::Value, String> {
 }
 
 #[tauri::command]
-fn copy_file_to_clipboard(path: String) -> Result<(), String> {
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::process::CommandExt;
-        Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-Command",
-                &format!("Set-Clipboard -Path '{}'", path.replace("'", "''")),
-            ])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .spawn()
-            .map_err(|e| e.to_string())?;
-        Ok(())
-    }
-    #[cfg(not(target_os = "windows"))]
-    {
-        Err("Not supported on this OS".to_string())
+fn copy_files_to_clipboard(paths: Vec<String>) -> error::Result<()> {
+    let paths: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    clipboard::copy_files(&paths).map_err(InchBoxError::platform)
+}
+
+#[derive(Serialize)]
+pub struct FilmstripResponse {
+    pub path: String,
+    pub frame_count: u32,
+    pub frame_width: u32,
+    pub frame_height: u32,
+}
+
+const FILMSTRIP_FRAMES: u32 = 10;
+const FILMSTRIP_FRAME_HEIGHT: u32 = 120;
+
+/// Render a sprite of evenly spaced frames for hover scrubbing over a video.
+#[tauri::command]
+async fn get_video_filmstrip(
+    file_id: String,
+    state: State<'_, AppState>,
+    queue: State<'_, ThumbnailQueue>,
+    thumb_cache: State<'_, ThumbnailCacheState>,
+    app: tauri::AppHandle,
+) -> error::Result<FilmstripResponse> {
+    let input_path = file_path_of(&state.data.lock().unwrap(), &file_id)?;
+    let cache_key = format!("{}_filmstrip", file_id);
+    let output_path = thumb_cache
+        .cache
+        .lock()
+        .unwrap()
+        .thumb_path(&cache_key, ThumbnailFormat::Jpeg);
+
+    let (file_size, modified_time) = stat(&input_path)?;
+
+    let cached = thumb_cache.cache.lock().unwrap().get_valid(
+        &cache_key,
+        Path::new(&input_path),
+        file_size,
+        modified_time,
+    );
+    if cached.is_none() {
+        let cache = thumb_cache.cache.clone();
+        let job_key = cache_key.clone();
+        let output = output_path.clone();
+        let job = queue.submit(&cache_key, None, Priority::Prefetch, move || {
+            let input = Path::new(&input_path);
+            let strip = thumbnail::video::generate_filmstrip(
+                input,
+                FILMSTRIP_FRAMES,
+                FILMSTRIP_FRAME_HEIGHT,
+            )?;
+            strip
+                .to_rgb8()
+                .save(&output)
+                .map_err(|e| fl!("error-save-filmstrip", reason = e.to_string()))?;
+            cache.lock().unwrap().update_metadata(
+                &job_key,
+                &file_id,
+                input,
+                file_size,
+                modified_time,
+                ThumbnailFormat::Jpeg,
+            );
+            Ok(output.to_string_lossy().to_string())
+        });
+        job.await??;
     }
+
+    // Frame width is derived from the sprite so cached strips need no extra metadata
+    let (width, height) = image::image_dimensions(&output_path)
+        .map_err(|e| InchBoxError::thumbnail(e.to_string()))?;
+    let _ = app.fs_scope().allow_file(&output_path);
+    Ok(FilmstripResponse {
+        path: output_path.to_string_lossy().to_string(),
+        frame_count: FILMSTRIP_FRAMES,
+        frame_width: width / FILMSTRIP_FRAMES,
+        frame_height: height,
+    })
 }
 
+/// Ids of the files whose placeholder has a dominant color near `color`
+/// (`#rrggbb`). Files without a placeholder yet never match.
 #[tauri::command]
-async fn get_file_thumbnail(
+fn filter_files_by_color(
+    color: String,
+    tolerance: Option<f32>,
+    state: State<AppState>,
+) -> error::Result<Vec<String>> {
+    let target = thumbnail::placeholder::parse_hex_color(&color)
+        .ok_or(InchBoxError::InvalidColor { color })?;
+    let tolerance = tolerance.unwrap_or(thumbnail::placeholder::DEFAULT_COLOR_TOLERANCE);
+
+    let data = state.data.lock().unwrap();
+    Ok(data
+        .files
+        .iter()
+        .filter(|f| {
+            f.placeholder
+                .as_ref()
+                .is_some_and(|p| p.has_color(target, tolerance))
+        })
+        .map(|f| f.id.clone())
+        .collect())
+}
+
+#[derive(Serialize)]
+pub struct PdfPageResponse {
+    pub path: String,
+    pub page: u16,
+    pub page_count: u16,
+}
+
+#[tauri::command]
+fn get_pdfium_status() -> thumbnail::pdf::PdfiumStatus {
+    thumbnail::pdf::status()
+}
+
+/// Render one page of a PDF, either fitted to `max_size` or at full preview
+/// resolution. Rendered pages live in the thumbnail cache like any thumbnail.
+#[tauri::command]
+async fn render_pdf_page(
     file_id: String,
+    page: u16,
+    max_size: Option<u32>,
     state: State<'_, AppState>,
+    queue: State<'_, ThumbnailQueue>,
+    thumb_cache: State<'_, ThumbnailCacheState>,
     app: tauri::AppHandle,
-) -> Result<String, String> {
-    #[cfg(target_os = "windows")]
-    {
-        let (input_path, thumbnail_path, thumb_dir) = {
-            let data = state.data.lock().unwrap();
-            let file = data
-                .files
-                .iter()
-                .find(|f| f.id == file_id)
-                .ok_or("File not found")?;
-            let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
-            let thumb_dir = app_data_dir.join("thumbnails");
-            if !thumb_dir.exists() {
-                std::fs::create_dir_all(&thumb_dir).map_err(|e| e.to_string())?;
+) -> error::Result<PdfPageResponse> {
+    let (input_path, known_page_count) = {
+        let data = state.data.lock().unwrap();
+        let file = data.files.iter().find(|f| f.id == file_id).ok_or_else(|| {
+            InchBoxError::FileNotFound {
+                file_id: file_id.clone(),
             }
-            let thumb_path = thumb_dir.join(format!("{}.jpg", file_id));
-            (file.path.clone(), thumb_path, thumb_dir)
+        })?;
+        // Usually extracted when the file was added, unless pdfium was missing then
+        let page_count = file.metadata.get("page_count").and_then(|v| v.as_u64());
+        (file.path.clone(), page_count)
+    };
+    let pdfium = thumbnail::pdf::status();
+    if !pdfium.available {
+        return Err(InchBoxError::PdfiumUnavailable {
+            message: pdfium.error.unwrap_or_default(),
+        });
+    }
+    let page_count = match known_page_count {
+        // pdfium counts pages in u16, so a larger stored count is bogus anyway
+        Some(count) => u16::try_from(count).unwrap_or(u16::MAX),
+        None => {
+            thumbnail::pdf::page_count(Path::new(&input_path)).map_err(InchBoxError::thumbnail)?
+        }
+    };
+    if page >= page_count {
+        return Err(InchBoxError::PageOutOfRange { page, page_count });
+    }
+
+    let resolution = max_size.map_or("full".to_string(), |s| s.to_string());
+    let cache_key = format!("{}_page{}_{}", file_id, page, resolution);
+    let output_path = thumb_cache
+        .cache
+        .lock()
+        .unwrap()
+        .thumb_path(&cache_key, ThumbnailFormat::Png);
+
+    let (file_size, modified_time) = stat(&input_path)?;
+
+    let cached = thumb_cache.cache.lock().unwrap().get_valid(
+        &cache_key,
+        Path::new(&input_path),
+        file_size,
+        modified_time,
+    );
+    if cached.is_some() {
+        thumb_cache.cache.lock().unwrap().touch(&cache_key);
+    } else {
+        let cache = thumb_cache.cache.clone();
+        let job_key = cache_key.clone();
+        let input_path = input_path.clone();
+        let output = output_path.clone();
+        let file_id = file_id.clone();
+        let job = queue.submit(&cache_key, None, Priority::Visible, move || {
+            let input = Path::new(&input_path);
+            let img = thumbnail::pdf::render_page(input, page, max_size)?;
+            img.save_with_format(&output, image::ImageFormat::Png)
+                .map_err(|e| fl!("error-save-page", reason = e.to_string()))?;
+            cache.lock().unwrap().update_metadata(
+                &job_key,
+                &file_id,
+                input,
+                file_size,
+                modified_time,
+                ThumbnailFormat::Png,
+            );
+            Ok(output.to_string_lossy().to_string())
+        });
+        job.await??;
+    }
+
+    let _ = app.fs_scope().allow_file(&output_path);
+    Ok(PdfPageResponse {
+        path: output_path.to_string_lossy().to_string(),
+        page,
+        page_count,
+    })
+}
+
+/// The newest log entries for the diagnostics view, oldest first.
+#[tauri::command]
+async fn get_recent_logs(
+    limit: Option<usize>,
+    min_level: Option<String>,
+    logs: State<'_, logging::LogState>,
+) -> error::Result<Vec<logging::LogEntry>> {
+    let limit = limit
+        .unwrap_or(logging::MAX_RECENT_LOGS)
+        .min(logging::MAX_RECENT_LOGS);
+    let dir = logs.dir.clone();
+    blocking(move || logging::recent(&dir, limit, min_level.as_deref())).await
+}
+
+/// Zip the recent logs with version, platform, library and cache statistics
+/// to `destination`, for attaching to bug reports. With `redact_paths` file
+/// and folder paths are replaced by placeholders.
+#[tauri::command]
+async fn export_diagnostics(
+    destination: String,
+    redact_paths: bool,
+    state: State<'_, AppState>,
+    settings: State<'_, SettingsState>,
+    thumb_cache: State<'_, ThumbnailCacheState>,
+    logs: State<'_, logging::LogState>,
+) -> error::Result<String> {
+    // Checking every path and redacting the logs can take a while; work on
+    // a copy so the library stays usable meanwhile
+    let data = state.data.lock().unwrap().clone();
+    let thumbnail_cache = thumb_cache.cache.lock().unwrap().stats();
+    let settings = settings.get();
+    let log_dir = logs.dir.clone();
+    let language = i18n::current_language();
+
+    blocking(move || -> error::Result<String> {
+        let report = diagnostics::Report {
+            generated_at: Utc::now().to_rfc3339(),
+            app: diagnostics::AppInfo::current(&data, language),
+            platform: diagnostics::PlatformInfo::current(),
+            library: diagnostics::LibraryStats::from_data(&data),
+            thumbnail_cache,
+            pdfium: thumbnail::pdf::status(),
+            settings,
+            redacted: redact_paths,
+        };
+        let redactor = redact_paths
+            .then(|| diagnostics::Redactor::new(&data))
+            .transpose()
+            .map_err(std::io::Error::other)?;
+
+        let log_files = logging::log_files(&log_dir);
+        diagnostics::write_bundle(
+            Path::new(&destination),
+            &report,
+            &log_files,
+            redactor.as_ref(),
+        )?;
+        tracing::info!(redacted = redact_paths, "Exported diagnostics");
+        Ok(destination)
+    })
+    .await?
+}
+
+/// Write the whole library to a portable archive at `destination`, with the
+/// cached thumbnails when `include_thumbnails` is set.
+#[tauri::command]
+async fn export_library(
+    destination: String,
+    include_thumbnails: bool,
+    state: State<'_, AppState>,
+    thumb_cache: State<'_, ThumbnailCacheState>,
+) -> error::Result<archive::Manifest> {
+    // Work on a snapshot so the library and the cache stay usable while the
+    // archive is written
+    let data = state.data.lock().unwrap().clone();
+    let thumbnails = if include_thumbnails {
+        let cache = thumb_cache.cache.lock().unwrap();
+        archive::thumbnail_entries(&data, &cache)
+    } else {
+        Vec::new()
+    };
+    let manifest =
+        blocking(move || archive::export(Path::new(&destination), &data, thumbnails)).await??;
+    tracing::info!(
+        files = manifest.file_count,
+        thumbnails = manifest.thumbnail_count,
+        "Exported library"
+    );
+    Ok(manifest)
+}
+
+/// Load an archive written by `export_library`. `merge` adds it to the
+/// current library under new ids, `replace` discards the current library.
+/// Imported tags named like existing ones are merged into them unless
+/// `tag_conflict` is `rename`. Reading the archive and its thumbnails runs
+/// off the library lock; `library-imported` is emitted with the report once
+/// the library changed, for the frontend to reload it.
+#[tauri::command]
+async fn import_library(
+    app: tauri::AppHandle,
+    source: String,
+    mode: archive::ImportMode,
+    tag_conflict: Option<archive::TagConflict>,
+    state: State<'_, AppState>,
+    thumb_cache: State<'_, ThumbnailCacheState>,
+) -> error::Result<archive::ImportReport> {
+    let (mut library, incoming) =
+        blocking(move || archive::LibraryArchive::open(Path::new(&source))).await??;
+    let incoming_paths: Vec<String> = incoming.files.iter().map(|f| f.path.clone()).collect();
+
+    let (mut report, file_ids, previous_ids, paths) = {
+        let mut data = state.data.lock().unwrap();
+        let previous_ids: Vec<String> = data.files.iter().map(|f| f.id.clone()).collect();
+        let (report, file_ids) = match mode {
+            archive::ImportMode::Merge => {
+                archive::merge(&mut data, incoming, tag_conflict.unwrap_or_default())
+            }
+            archive::ImportMode::Replace => archive::replace(&mut data, incoming),
         };
+        save_to_disk(&data, &state.file_path);
+        // Source paths of the imported files, by their library id
+        let imported: HashSet<&String> = file_ids.values().collect();
+        let paths: HashMap<String, String> = data
+            .files
+            .iter()
+            .filter(|f| imported.contains(&f.id))
+            .map(|f| (f.id.clone(), f.path.clone()))
+            .collect();
+        (report, file_ids, previous_ids, paths)
+    };
+
+    // Thumbnails of the replaced library are deleted with the import below
+    let replaced_thumbnails: Vec<PathBuf> = if mode == archive::ImportMode::Replace {
+        let mut cache = thumb_cache.cache.lock().unwrap();
+        previous_ids
+            .iter()
+            .flat_map(|id| cache.remove_file_entries(id))
+            .collect()
+    } else {
+        Vec::new()
+    };
 
-        // Get file metadata for cache validation
-        let file_metadata = std::fs::metadata(&input_path)
-            .map_err(|e| format!("Failed to get file metadata: {}", e))?;
-        let file_size = file_metadata.len();
-        let modified_time = file_metadata
-            .modified()
-            .map_err(|e| format!("Failed to get modified time: {}", e))?
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| format!("Invalid modified time: {}", e))?
-            .as_secs() as i64;
-
-        // Check cache validity
-        let cache_valid = {
-            if let Ok(cache) = cache::ThumbnailCache::new(&thumb_dir) {
-                cache.is_valid(&file_id, Path::new(&input_path), file_size, modified_time)
+    let from_version = library.manifest.app_version.clone();
+    let cache = thumb_cache.cache.clone();
+    let scope_app = app.clone();
+    let (thumbnails_imported, files_missing) = blocking(move || {
+        for thumb_path in replaced_thumbnails {
+            let _ = fs::remove_file(thumb_path);
+        }
+        let imported = library.import_thumbnails(&file_ids, &paths, &cache);
+        let missing = archive::count_missing(incoming_paths.iter());
+
+        let scope = scope_app.fs_scope();
+        for path in paths.values().map(Path::new) {
+            let ret = if path.is_dir() {
+                scope.allow_directory(path, true)
+            } else if path.exists() {
+                scope.allow_file(path)
             } else {
-                false
+                continue;
+            };
+            if let Err(e) = ret {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to extend fs scope");
+            }
+        }
+        (imported, missing)
+    })
+    .await?;
+    report.thumbnails_imported = thumbnails_imported;
+    report.files_missing = files_missing;
+
+    tracing::info!(
+        mode = ?mode,
+        from_version = %from_version,
+        files_added = report.files_added,
+        files_merged = report.files_merged,
+        tags_added = report.tags_added,
+        thumbnails = report.thumbnails_imported,
+        "Imported library"
+    );
+    if let Err(e) = app.emit("library-imported", &report) {
+        tracing::warn!(error = %e, "Failed to emit library-imported");
+    }
+    Ok(report)
+}
+
+/// Write the files with `file_ids`, in that order, or the whole library to
+/// `destination` as CSV or JSON Lines. `columns` are `name`, `path`,
+/// `extension`, `size`, `mime_type`, `added_at`, `tags`, `groups` and
+/// `metadata.<field>`. Returns the number of rows written.
+#[tauri::command]
+async fn export_file_list(
+    file_ids: Option<Vec<String>>,
+    destination: String,
+    format: listing::ListingFormat,
+    columns: Vec<String>,
+    state: State<'_, AppState>,
+) -> error::Result<usize> {
+    let columns = columns
+        .iter()
+        .map(|c| listing::Column::parse(c))
+        .collect::<error::Result<Vec<_>>>()?;
+
+    // Copy out what the listing needs so the file is written off the lock
+    let (files, tags, groups) = {
+        let data = state.data.lock().unwrap();
+        let files: Vec<FileItem> = match &file_ids {
+            Some(file_ids) => {
+                let by_id: HashMap<&str, &FileItem> =
+                    data.files.iter().map(|f| (f.id.as_str(), f)).collect();
+                let missing: Vec<String> = file_ids
+                    .iter()
+                    .filter(|id| !by_id.contains_key(id.as_str()))
+                    .cloned()
+                    .collect();
+                if !missing.is_empty() {
+                    return Err(InchBoxError::FilesNotFound { file_ids: missing });
+                }
+                file_ids
+                    .iter()
+                    .map(|id| by_id[id.as_str()].clone())
+                    .collect()
             }
+            None => data.files.clone(),
         };
+        (files, data.tags.clone(), data.groups.clone())
+    };
+
+    let count = blocking(move || {
+        listing::write(
+            Path::new(&destination),
+            format,
+            &columns,
+            &tags,
+            &groups,
+            files.iter(),
+        )
+    })
+    .await??;
+    tracing::info!(rows = count, format = ?format, "Exported file list");
+    Ok(count)
+}
+
+#[derive(Serialize)]
+pub struct XmpSyncResponse {
+    /// Files that got tags from keywords in their XMP
+    pub files_updated: usize,
+    pub sidecars_written: usize,
+    pub created_tags: Vec<Tag>,
+    /// Keywords left out, their names sit elsewhere among the tags
+    pub tag_conflicts: Vec<TagPathConflict>,
+    /// Paths whose XMP couldn't be read or written
+    pub failed: Vec<String>,
+}
 
-        if !cache_valid {
-            // Simple limit: if too many concurrent, just proceed (spawn_blocking has its own limits)
-            // For better control, consider using a proper async semaphore crate
-            let input = input_path.clone();
-            let output = thumbnail_path.clone();
-            let result = tauri::async_runtime::spawn_blocking(move || {
-                thumbnail::generate_thumbnail(Path::new(&input), &output, 320)
+/// Sync tags with XMP both ways for `file_ids`, or the whole library:
+/// keywords from sidecars or embedded XMP are attached as tags, then tags
+/// missing from the sidecars are added to them. Neither side loses tags or
+/// keywords. The library is only locked to attach, not while reading or
+/// writing the files.
+#[tauri::command]
+async fn sync_xmp_tags(
+    file_ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+) -> error::Result<XmpSyncResponse> {
+    let targets: Vec<(String, PathBuf, String)> = {
+        let data = state.data.lock().unwrap();
+        if let Some(file_ids) = &file_ids {
+            let missing: Vec<String> = file_ids
+                .iter()
+                .filter(|id| !data.files.iter().any(|f| &f.id == *id))
+                .cloned()
+                .collect();
+            if !missing.is_empty() {
+                return Err(InchBoxError::FilesNotFound { file_ids: missing });
+            }
+        }
+        let wanted: Option<HashSet<&String>> = file_ids.as_ref().map(|ids| ids.iter().collect());
+        data.files
+            .iter()
+            .filter(|f| wanted.as_ref().is_none_or(|w| w.contains(&f.id)))
+            .filter(|f| f.mime_type != "inode/directory")
+            .map(|f| (f.id.clone(), PathBuf::from(&f.path), f.mime_type.clone()))
+            .collect()
+    };
+
+    let mut response = XmpSyncResponse {
+        files_updated: 0,
+        sidecars_written: 0,
+        created_tags: Vec::new(),
+        tag_conflicts: Vec::new(),
+        failed: Vec::new(),
+    };
+    let found = blocking(move || {
+        targets
+            .into_iter()
+            .filter(|(_, path, _)| path.exists())
+            .map(|(id, path, mime_type)| {
+                let keywords = xmp::read(&path, &mime_type);
+                (id, path, mime_type, keywords)
             })
-            .await
-            .map_err(|e| e.to_string())?;
+            .collect::<Vec<_>>()
+    })
+    .await?;
+
+    // Attach under the lock, noting what each sidecar should then have
+    let mut writes: Vec<(PathBuf, String, Vec<Vec<String>>)> = Vec::new();
+    {
+        let mut data = state.data.lock().unwrap();
+        for (id, path, mime_type, keywords) in found {
+            let keywords = match keywords {
+                Ok(keywords) => keywords,
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Failed to read XMP keywords");
+                    response.failed.push(path.display().to_string());
+                    continue;
+                }
+            };
+            // Removed from the library meanwhile
+            let Some(i) = data.files.iter().position(|f| f.id == id) else {
+                continue;
+            };
 
-            if let Err(e) = result {
-                return Err(format!("Failed to generate thumbnail: {}", e));
+            let mut added = false;
+            for tag_path in keywords.tag_paths() {
+                match data.ensure_tag_path(&tag_path, &mut response.created_tags) {
+                    Ok(Some(id)) => {
+                        let file = &mut data.files[i];
+                        if !file.tag_ids.contains(&id) {
+                            file.tag_ids.push(id);
+                            added = true;
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(conflict) => {
+                        tracing::warn!(
+                            path = %path.display(),
+                            tag_path = ?conflict.path,
+                            existing = ?conflict.existing,
+                            "Tag name already used elsewhere"
+                        );
+                        response.tag_conflicts.push(conflict);
+                    }
+                }
             }
+            if added {
+                response.files_updated += 1;
+            }
+            writes.push((path, mime_type, data.file_tag_paths(&data.files[i])));
+        }
+        if response.files_updated > 0 || !response.created_tags.is_empty() {
+            save_to_disk(&data, &state.file_path);
+        }
+    }
 
-            // Update cache metadata
-            if let Ok(mut cache) = cache::ThumbnailCache::new(&thumb_dir) {
-                cache.update_metadata(&file_id, Path::new(&input_path), file_size, modified_time);
-                let _ = cache.save();
+    let (sidecars_written, failed) = blocking(move || {
+        let mut written = 0;
+        let mut failed = Vec::new();
+        for (path, mime_type, tag_paths) in writes {
+            match xmp::update(&path, &mime_type, &[], &tag_paths) {
+                Ok(true) => written += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Failed to write XMP sidecar");
+                    failed.push(path.display().to_string());
+                }
             }
         }
+        (written, failed)
+    })
+    .await?;
+    response.sidecars_written = sidecars_written;
+    response.failed.extend(failed);
 
-        let _ = app.fs_scope().allow_file(&thumbnail_path);
-        Ok(thumbnail_path.to_string_lossy().to_string())
-    }
-    #[cfg(not(target_os = "windows"))]
-    {
-        Err("Not supported on this OS".to_string())
+    tracing::info!(
+        files_updated = response.files_updated,
+        sidecars_written = response.sidecars_written,
+        created_tags = response.created_tags.len(),
+        tag_conflicts = response.tag_conflicts.len(),
+        failed = response.failed.len(),
+        "Synced XMP tags"
+    );
+    Ok(response)
+}
+
+#[tauri::command]
+fn get_cache_stats(thumb_cache: State<ThumbnailCacheState>) -> CacheStats {
+    thumb_cache.cache.lock().unwrap().stats()
+}
+
+/// Delete every cached thumbnail. Returns how many files were removed.
+#[tauri::command]
+async fn clear_thumbnail_cache(
+    thumb_cache: State<'_, ThumbnailCacheState>,
+) -> error::Result<usize> {
+    let cache_dir = {
+        let mut cache = thumb_cache.cache.lock().unwrap();
+        cache.clear();
+        cache.cache_dir().to_path_buf()
+    };
+    blocking(move || cache::remove_thumbnail_files(&cache_dir)).await
+}
+
+/// Whether `get_file_thumbnail` has an extractor for the type, so the
+/// frontend only asks for thumbnails that can exist
+#[tauri::command]
+fn supports_thumbnail(
+    mime_type: String,
+    extension: String,
+    registry: State<ExtractorRegistry>,
+) -> bool {
+    registry.can_thumbnail(&mime_type, &extension.to_lowercase())
+}
+
+/// Withdraw `get_file_thumbnail` requests by the `request_id` they were
+/// made with; other requests for the same thumbnail keep waiting.
+#[tauri::command]
+fn cancel_thumbnails(request_ids: Vec<String>, queue: State<ThumbnailQueue>) -> usize {
+    queue.cancel(&request_ids)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn get_file_thumbnail(
+    file_id: String,
+    size: Option<ThumbnailSize>,
+    priority: Option<Priority>,
+    request_id: Option<String>,
+    state: State<'_, AppState>,
+    registry: State<'_, ExtractorRegistry>,
+    settings: State<'_, SettingsState>,
+    queue: State<'_, ThumbnailQueue>,
+    thumb_cache: State<'_, ThumbnailCacheState>,
+    app: tauri::AppHandle,
+) -> error::Result<String> {
+    let size = size.unwrap_or_default();
+    let max_size = size.pixels();
+    let cache_key = cache::ThumbnailCache::get_cache_key(&file_id, size);
+
+    let (input_path, output_stem, needs_placeholder) = {
+        let data = state.data.lock().unwrap();
+        let file = data.files.iter().find(|f| f.id == file_id).ok_or_else(|| {
+            InchBoxError::FileNotFound {
+                file_id: file_id.clone(),
+            }
+        })?;
+        let cache = thumb_cache.cache.lock().unwrap();
+        let thumb_dir = cache.cache_dir();
+        if !thumb_dir.exists() {
+            std::fs::create_dir_all(thumb_dir)?;
+        }
+        (
+            file.path.clone(),
+            thumb_dir.join(&cache_key),
+            file.placeholder.is_none(),
+        )
+    };
+
+    // Get file metadata for cache validation
+    let (file_size, modified_time) = stat(&input_path)?;
+
+    let settings = settings.get();
+
+    // Prefer a thumbnail the desktop already made for this file
+    #[cfg(target_os = "linux")]
+    if settings.read_freedesktop_thumbnails {
+        if let Some(shared_path) =
+            thumbnail::freedesktop::lookup(Path::new(&input_path), max_size, modified_time)
+        {
+            if needs_placeholder {
+                if let Ok(img) = image::open(&shared_path) {
+                    store_placeholder(&app, &file_id, &img);
+                }
+            }
+            let _ = app.fs_scope().allow_file(&shared_path);
+            return Ok(shared_path.to_string_lossy().to_string());
+        }
     }
+
+    // Check cache validity
+    let cached_path = {
+        let mut cache = thumb_cache.cache.lock().unwrap();
+        let cached_path =
+            cache.get_valid(&cache_key, Path::new(&input_path), file_size, modified_time);
+        if cached_path.is_some() {
+            cache.touch(&cache_key);
+        }
+        cached_path
+    };
+
+    let thumbnail_path = match cached_path {
+        Some(path) => {
+            // Thumbnails cached before placeholders existed
+            if needs_placeholder {
+                if let Ok(img) = image::open(&path) {
+                    store_placeholder(&app, &file_id, &img);
+                }
+            }
+            path
+        }
+        None => {
+            let registry = registry.inner().clone();
+            let job_app = app.clone();
+            let cache = thumb_cache.cache.clone();
+            let write_shared = settings.write_freedesktop_thumbnails;
+            let job_key = cache_key.clone();
+            let job = queue.submit(&cache_key, request_id, priority.unwrap_or_default(), move || {
+                let input = Path::new(&input_path);
+                let thumb =
+                    thumbnail::generate_thumbnail(&registry, input, &output_stem, max_size)?;
+                #[cfg(target_os = "linux")]
+                if write_shared {
+                    if let Err(e) =
+                        thumbnail::freedesktop::store(&thumb.image, input, max_size, modified_time)
+                    {
+                        tracing::warn!(path = %input.display(), error = %e, "Failed to write shared thumbnail");
+                    }
+                }
+                #[cfg(not(target_os = "linux"))]
+                let _ = write_shared;
+
+                // The content may have changed, so always refresh the placeholder
+                store_placeholder(&job_app, &file_id, &thumb.image);
+
+                // Update cache metadata (persisted in the background)
+                cache.lock().unwrap().update_metadata(
+                    &job_key,
+                    &file_id,
+                    input,
+                    file_size,
+                    modified_time,
+                    thumb.format,
+                );
+                Ok(thumb.path.to_string_lossy().to_string())
+            });
+
+            match job.await? {
+                Ok(path) => path.into(),
+                Err(JobError::Cancelled) => return Err(InchBoxError::Cancelled),
+                Err(JobError::Failed(reason)) => {
+                    tracing::warn!(key = %cache_key, error = %reason, "Thumbnail generation failed");
+                    return Err(InchBoxError::thumbnail(reason));
+                }
+            }
+        }
+    };
+
+    let _ = app.fs_scope().allow_file(&thumbnail_path);
+    Ok(thumbnail_path.to_string_lossy().to_string())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    run_with_extractors(ExtractorRegistry::with_defaults())
+}
+
+/// Run the app with a custom set of metadata extractors, e.g. the defaults
+/// plus in-house formats registered on top.
+pub fn run_with_extractors(registry: ExtractorRegistry) {
     tauri::Builder::default()
+        .manage(registry)
+        .manage(ThumbnailQueue::new(MAX_CONCURRENT_THUMBNAILS))
+        .manage(TagMirror::new())
         .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.show();
@@ -492,7 +1654,35 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
+            // First, so that loading the library is already logged
+            let log_dir = app
+                .path()
+                .app_data_dir()
+                .unwrap_or_else(|_| std::path::PathBuf::from("."))
+                .join("logs");
+            app.manage(logging::init(&log_dir));
+            tracing::info!(version = env!("CARGO_PKG_VERSION"), "Starting InchBox");
+
             let state = AppState::new(app.handle());
+            {
+                let mut data = state.data.lock().unwrap();
+                if data.record_app_version(env!("CARGO_PKG_VERSION"), Utc::now().timestamp_millis())
+                {
+                    save_to_disk(&data, &state.file_path);
+                }
+            }
+            app.manage(SettingsState::new(app.handle()));
+            if let Some(language) = app.state::<SettingsState>().get().language {
+                if let Err(e) = i18n::select(Some(&language)) {
+                    tracing::warn!(language, error = %e, "Failed to select language");
+                }
+            }
+            thumbnail::pdf::configure(
+                app.state::<SettingsState>()
+                    .get()
+                    .pdfium_library_path
+                    .map(std::path::PathBuf::from),
+            );
 
             // Manually re-hydrate the filesystem scope from the saved database
             {
@@ -508,51 +1698,48 @@ pub fn run() {
                         }
                     }
                 }
-                // Also allow the thumbnail directory
-                if let Ok(app_data_dir) = app.path().app_data_dir() {
-                    let thumb_dir = app_data_dir.join("thumbnails");
-                    if !thumb_dir.exists() {
-                        let _ = std::fs::create_dir_all(&thumb_dir);
-                    }
-                    let _ = scope.allow_directory(&thumb_dir, true);
-                }
             }
 
-            app.manage(state);
-
-            // Cleanup orphaned thumbnails on startup (limit to first 100)
-            {
-                if let Ok(app_data_dir) = app.path().app_data_dir() {
-                    let thumb_dir = app_data_dir.join("thumbnails");
-                    let state_guard = app.state::<AppState>();
-                    let data = state_guard.data.lock().unwrap();
+            // Also allow the thumbnail directory
+            let thumb_dir = app
+                .path()
+                .app_data_dir()
+                .unwrap_or_else(|_| std::path::PathBuf::from("."))
+                .join("thumbnails");
+            if !thumb_dir.exists() {
+                let _ = std::fs::create_dir_all(&thumb_dir);
+            }
+            let _ = app.fs_scope().allow_directory(&thumb_dir, true);
+            let max_cache_bytes = app
+                .state::<SettingsState>()
+                .get()
+                .max_thumbnail_cache_bytes();
+            let thumb_cache = ThumbnailCacheState::new(&thumb_dir, max_cache_bytes);
 
-                    // Get list of valid file IDs
-                    let valid_ids: Vec<String> = data.files.iter().map(|f| f.id.clone()).collect();
+            // Cleanup orphaned thumbnails on startup
+            let valid_ids: Vec<String> = state
+                .data
+                .lock()
+                .unwrap()
+                .files
+                .iter()
+                .map(|f| f.id.clone())
+                .collect();
+            thumb_cache.sweep_orphans(valid_ids);
 
-                    // Clean up orphaned thumbnails
-                    if let Ok(cache) = cache::ThumbnailCache::new(&thumb_dir) {
-                        let _ = cache.cleanup_orphaned_thumbnails(&thumb_dir, &valid_ids, 100);
-                    }
-                }
-            }
+            app.manage(state);
+            app.manage(thumb_cache);
 
             // System Tray Setup
-            let quit_i = MenuItem::with_id(
-                app,
-                "quit",
-                &get_localized_string("tray-menu-quit"),
-                true,
-                None::<&str>,
-            )?;
-            let show_i = MenuItem::with_id(
-                app,
-                "show",
-                &get_localized_string("tray-menu-show"),
-                true,
-                None::<&str>,
-            )?;
+            let quit_i =
+                MenuItem::with_id(app, "quit", &fl!("tray-menu-quit"), true, None::<&str>)?;
+            let show_i =
+                MenuItem::with_id(app, "show", &fl!("tray-menu-show"), true, None::<&str>)?;
             let menu = Menu::with_items(app, &[&show_i, &quit_i])?;
+            app.manage(TrayMenu {
+                show: show_i,
+                quit: quit_i,
+            });
 
             let _tray = TrayIconBuilder::with_id("tray")
                 .icon(app.default_window_icon().unwrap().clone())
@@ -608,6 +1795,9 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             get_initial_data,
+            get_settings,
+            update_settings,
+            set_language,
             add_files,
             delete_files,
             create_tag_group,
@@ -620,11 +1810,38 @@ pub fn run() {
             attach_tag,
             detach_tag,
             open_file_default,
+            get_open_with_apps,
+            open_file_with,
             show_in_explorer,
-            copy_file_to_clipboard,
+            copy_files_to_clipboard,
             get_file_thumbnail,
+            supports_thumbnail,
+            cancel_thumbnails,
+            get_video_filmstrip,
+            get_pdfium_status,
+            filter_files_by_color,
+            render_pdf_page,
+            get_cache_stats,
+            clear_thumbnail_cache,
+            get_recent_logs,
+            export_diagnostics,
+            export_library,
+            import_library,
+            export_file_list,
+            sync_xmp_tags,
             get_file_info
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Don't lose changes still waiting for the flush threads
+                if let Some(thumb_cache) = app.try_state::<ThumbnailCacheState>() {
+                    thumb_cache.flush();
+                }
+                if let Some(state) = app.try_state::<AppState>() {
+                    state.flush();
+                }
+            }
+        });
 }
@@ -0,0 +1,404 @@
+use crate::error::InchBoxError;
+use crate::models::{FileItem, Tag, TagGroup};
+use chrono::{TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Separates the levels of a tag path: `Travel/Europe/Paris`
+const TAG_PATH_SEPARATOR: &str = "/";
+
+/// Marks a separator or escape that is part of a tag name: `AC\/DC`
+const TAG_PATH_ESCAPE: char = '\\';
+
+/// Separates tags and groups sharing one CSV cell
+const CSV_LIST_SEPARATOR: &str = "; ";
+
+/// Prefix of metadata columns: `metadata.width`
+const METADATA_PREFIX: &str = "metadata.";
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListingFormat {
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Name,
+    Path,
+    Extension,
+    Size,
+    MimeType,
+    AddedAt,
+    /// Full paths of the attached tags, with `/` and `\` inside a name
+    /// escaped by a `\`
+    Tags,
+    /// Names of the groups the attached tags belong to
+    Groups,
+    /// A field filled by the metadata extractors
+    Metadata(String),
+}
+
+impl Column {
+    /// Parse a column name as used by the frontend and in the header row.
+    pub fn parse(name: &str) -> Result<Self, InchBoxError> {
+        let column = match name {
+            "name" => Column::Name,
+            "path" => Column::Path,
+            "extension" => Column::Extension,
+            "size" => Column::Size,
+            "mime_type" => Column::MimeType,
+            "added_at" => Column::AddedAt,
+            "tags" => Column::Tags,
+            "groups" => Column::Groups,
+            _ => match name.strip_prefix(METADATA_PREFIX) {
+                Some(key) if !key.is_empty() => Column::Metadata(key.to_string()),
+                _ => {
+                    return Err(InchBoxError::UnknownColumn {
+                        column: name.to_string(),
+                    })
+                }
+            },
+        };
+        Ok(column)
+    }
+
+    fn header(&self) -> String {
+        match self {
+            Column::Name => "name".to_string(),
+            Column::Path => "path".to_string(),
+            Column::Extension => "extension".to_string(),
+            Column::Size => "size".to_string(),
+            Column::MimeType => "mime_type".to_string(),
+            Column::AddedAt => "added_at".to_string(),
+            Column::Tags => "tags".to_string(),
+            Column::Groups => "groups".to_string(),
+            Column::Metadata(key) => format!("{}{}", METADATA_PREFIX, key),
+        }
+    }
+}
+
+/// Resolves tag ids to their path in the hierarchy and their group name.
+struct TagLookup<'a> {
+    tags: HashMap<&'a str, &'a Tag>,
+    groups: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> TagLookup<'a> {
+    fn new(tags: &'a [Tag], groups: &'a [TagGroup]) -> Self {
+        Self {
+            tags: tags.iter().map(|t| (t.id.as_str(), t)).collect(),
+            groups: groups
+                .iter()
+                .map(|g| (g.id.as_str(), g.name.as_str()))
+                .collect(),
+        }
+    }
+
+    fn path(&self, tag_id: &str) -> Option<String> {
+        let mut names: Vec<String> = Vec::new();
+        let mut current = self.tags.get(tag_id).copied();
+        while let Some(tag) = current {
+            // A cycle can only come from a hand-edited library
+            if names.len() > self.tags.len() {
+                break;
+            }
+            names.push(escape_tag_name(&tag.name));
+            current = tag
+                .parent_id
+                .as_deref()
+                .and_then(|id| self.tags.get(id).copied());
+        }
+        if names.is_empty() {
+            return None;
+        }
+        names.reverse();
+        Some(names.join(TAG_PATH_SEPARATOR))
+    }
+
+    fn tag_paths(&self, file: &FileItem) -> Vec<String> {
+        file.tag_ids.iter().filter_map(|id| self.path(id)).collect()
+    }
+
+    fn group_names(&self, file: &FileItem) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for tag_id in &file.tag_ids {
+            let group = self
+                .tags
+                .get(tag_id.as_str())
+                .and_then(|t| t.group_id.as_deref())
+                .and_then(|id| self.groups.get(id));
+            if let Some(name) = group {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names
+    }
+}
+
+/// Escape the separator in a tag name, so `AC/DC` stays apart from `DC`
+/// nested under `AC`
+fn escape_tag_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == TAG_PATH_ESCAPE || TAG_PATH_SEPARATOR.contains(c) {
+            escaped.push(TAG_PATH_ESCAPE);
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn added_at(file: &FileItem) -> String {
+    Utc.timestamp_millis_opt(file.added_at)
+        .single()
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_default()
+}
+
+fn json_value(file: &FileItem, column: &Column, lookup: &TagLookup) -> Value {
+    match column {
+        Column::Name => Value::from(file.name.as_str()),
+        Column::Path => Value::from(file.path.as_str()),
+        Column::Extension => Value::from(file.extension.as_str()),
+        Column::Size => Value::from(file.size),
+        Column::MimeType => Value::from(file.mime_type.as_str()),
+        Column::AddedAt => Value::from(added_at(file)),
+        Column::Tags => Value::from(lookup.tag_paths(file)),
+        Column::Groups => Value::from(lookup.group_names(file)),
+        Column::Metadata(key) => file.metadata.get(key).cloned().unwrap_or(Value::Null),
+    }
+}
+
+fn csv_value(file: &FileItem, column: &Column, lookup: &TagLookup) -> String {
+    let text = match column {
+        Column::Size => return file.size.to_string(),
+        Column::Tags => lookup.tag_paths(file).join(CSV_LIST_SEPARATOR),
+        Column::Groups => lookup.group_names(file).join(CSV_LIST_SEPARATOR),
+        _ => match json_value(file, column, lookup) {
+            Value::Null => String::new(),
+            Value::String(text) => text,
+            // A negative number is no formula
+            number @ Value::Number(_) => return number.to_string(),
+            // Arrays and objects as JSON text
+            other => other.to_string(),
+        },
+    };
+    escape_formula(text)
+}
+
+/// Spreadsheets evaluate cells starting with these, so a file named
+/// `=HYPERLINK(...)` would run as a formula when the listing is opened
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Quote text that would be read as a formula, the way spreadsheets expect
+fn escape_formula(text: String) -> String {
+    if text.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", text)
+    } else {
+        text
+    }
+}
+
+/// Write one row per file to `destination`, in the order given, as rows
+/// are produced. Returns the number of rows.
+pub fn write<'a>(
+    destination: &Path,
+    format: ListingFormat,
+    columns: &[Column],
+    tags: &[Tag],
+    groups: &[TagGroup],
+    files: impl Iterator<Item = &'a FileItem>,
+) -> io::Result<usize> {
+    let lookup = TagLookup::new(tags, groups);
+    let writer = BufWriter::new(File::create(destination)?);
+    let mut count = 0;
+
+    match format {
+        ListingFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(writer);
+            csv.write_record(columns.iter().map(Column::header))?;
+            for file in files {
+                csv.write_record(columns.iter().map(|c| csv_value(file, c, &lookup)))?;
+                count += 1;
+            }
+            csv.flush()?;
+        }
+        ListingFormat::Jsonl => {
+            let mut writer = writer;
+            for file in files {
+                let row: Map<String, Value> = columns
+                    .iter()
+                    .map(|c| (c.header(), json_value(file, c, &lookup)))
+                    .collect();
+                serde_json::to_writer(&mut writer, &row)?;
+                writer.write_all(b"\n")?;
+                count += 1;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AppData;
+
+    fn tag(id: &str, name: &str, parent_id: Option<&str>, group_id: Option<&str>) -> Tag {
+        Tag {
+            id: id.to_string(),
+            name: name.to_string(),
+            parent_id: parent_id.map(str::to_string),
+            group_id: group_id.map(str::to_string),
+        }
+    }
+
+    fn library() -> AppData {
+        let file = FileItem {
+            id: "f1".to_string(),
+            name: "paris.jpg".to_string(),
+            path: "/photos/paris.jpg".to_string(),
+            extension: "jpg".to_string(),
+            size: 2048,
+            mime_type: "image/jpeg".to_string(),
+            added_at: 0,
+            tag_ids: vec!["paris".to_string(), "film".to_string()],
+            metadata: HashMap::from([("width".to_string(), Value::from(640))]),
+            placeholder: None,
+        };
+        AppData {
+            files: vec![file],
+            tags: vec![
+                tag("travel", "Travel", None, Some("places")),
+                tag("paris", "Paris", Some("travel"), Some("places")),
+                tag("film", "Film", None, None),
+            ],
+            groups: vec![TagGroup {
+                id: "places".to_string(),
+                name: "Places".to_string(),
+                color: None,
+            }],
+            ..AppData::default()
+        }
+    }
+
+    #[test]
+    fn parses_what_it_writes_as_header() {
+        for name in [
+            "name",
+            "path",
+            "extension",
+            "size",
+            "mime_type",
+            "added_at",
+            "tags",
+            "groups",
+            "metadata.width",
+        ] {
+            assert_eq!(Column::parse(name).unwrap().header(), name);
+        }
+        assert!(Column::parse("metadata.").is_err());
+        assert!(Column::parse("colour").is_err());
+    }
+
+    #[test]
+    fn resolves_tag_paths_and_groups() {
+        let data = library();
+        let lookup = TagLookup::new(&data.tags, &data.groups);
+        let file = &data.files[0];
+        assert_eq!(lookup.tag_paths(file), vec!["Travel/Paris", "Film"]);
+        assert_eq!(lookup.group_names(file), vec!["Places"]);
+        assert_eq!(lookup.path("unknown"), None);
+    }
+
+    #[test]
+    fn escapes_separators_inside_tag_names() {
+        let mut data = library();
+        data.tags.push(tag("acdc", "AC/DC", None, None));
+        data.tags.push(tag("ac", "AC", None, None));
+        data.tags.push(tag("dc", "DC", Some("ac"), None));
+        data.tags.push(tag("backslash", "a\\b", None, None));
+        let lookup = TagLookup::new(&data.tags, &data.groups);
+        assert_eq!(lookup.path("acdc").as_deref(), Some("AC\\/DC"));
+        assert_eq!(lookup.path("dc").as_deref(), Some("AC/DC"));
+        assert_eq!(lookup.path("backslash").as_deref(), Some("a\\\\b"));
+    }
+
+    #[test]
+    fn writes_csv_and_json_lines() {
+        let data = library();
+        let columns: Vec<Column> = [
+            "name",
+            "size",
+            "added_at",
+            "tags",
+            "metadata.width",
+            "metadata.iso",
+        ]
+        .iter()
+        .map(|c| Column::parse(c).unwrap())
+        .collect();
+        let destination =
+            std::env::temp_dir().join(format!("inchbox-listing-{}", std::process::id()));
+
+        let count = write(
+            &destination,
+            ListingFormat::Csv,
+            &columns,
+            &data.tags,
+            &data.groups,
+            data.files.iter(),
+        )
+        .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            std::fs::read_to_string(&destination).unwrap(),
+            "name,size,added_at,tags,metadata.width,metadata.iso\n\
+             paris.jpg,2048,1970-01-01T00:00:00+00:00,Travel/Paris; Film,640,\n"
+        );
+
+        write(
+            &destination,
+            ListingFormat::Jsonl,
+            &columns,
+            &data.tags,
+            &data.groups,
+            data.files.iter(),
+        )
+        .unwrap();
+        let line = std::fs::read_to_string(&destination).unwrap();
+        let row: Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(row["tags"], serde_json::json!(["Travel/Paris", "Film"]));
+        assert_eq!(row["metadata.width"], 640);
+        assert_eq!(row["metadata.iso"], Value::Null);
+        let _ = std::fs::remove_file(&destination);
+    }
+
+    #[test]
+    fn escapes_formulas_in_csv() {
+        let mut data = library();
+        data.files[0].name = "=HYPERLINK(\"http://example.com\")".to_string();
+        data.files[0].extension = "@cmd".to_string();
+        data.files[0]
+            .metadata
+            .insert("exposure".to_string(), Value::from(-0.3));
+        let lookup = TagLookup::new(&data.tags, &data.groups);
+        let file = &data.files[0];
+        let value = |name: &str| csv_value(file, &Column::parse(name).unwrap(), &lookup);
+
+        assert_eq!(value("name"), "'=HYPERLINK(\"http://example.com\")");
+        assert_eq!(value("extension"), "'@cmd");
+        assert_eq!(value("metadata.exposure"), "-0.3");
+        assert_eq!(value("path"), "/photos/paris.jpg");
+    }
+}
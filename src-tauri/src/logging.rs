@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+const LOG_PREFIX: &str = "inchbox";
+const LOG_SUFFIX: &str = "log";
+
+/// One file per day; older ones are deleted
+const MAX_LOG_FILES: usize = 7;
+
+/// Upper bound for `get_recent_logs`
+pub const MAX_RECENT_LOGS: usize = 2000;
+
+/// Where logs go, kept in managed state. Dropping the guard flushes and
+/// stops the background writer, so it lives as long as the app.
+pub struct LogState {
+    pub dir: PathBuf,
+    _guard: Option<WorkerGuard>,
+}
+
+/// Log to stdout and to daily rotating JSON-lines files in `dir`. The level
+/// defaults to `info` and can be changed with `RUST_LOG`.
+pub fn init(dir: &Path) -> LogState {
+    let filter = || EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let console = fmt::layer().with_filter(filter());
+
+    let appender = fs::create_dir_all(dir)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(LOG_PREFIX)
+                .filename_suffix(LOG_SUFFIX)
+                .max_log_files(MAX_LOG_FILES)
+                .build(dir)
+                .map_err(|e| e.to_string())
+        });
+
+    let (file_layer, guard, file_error) = match appender {
+        Ok(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = fmt::layer()
+                .json()
+                .with_ansi(false)
+                .with_writer(writer)
+                .with_filter(filter());
+            (Some(layer), Some(guard), None)
+        }
+        Err(e) => (None, None, Some(e)),
+    };
+
+    // Fails when a subscriber is already set, e.g. by an embedding app
+    let _ = tracing_subscriber::registry()
+        .with(console)
+        .with(file_layer)
+        .try_init();
+    if let Some(e) = file_error {
+        tracing::warn!(dir = %dir.display(), error = %e, "Logging to the console only");
+    }
+
+    LogState {
+        dir: dir.to_path_buf(),
+        _guard: guard,
+    }
+}
+
+/// A log line as written by the JSON formatter.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    #[serde(default)]
+    pub target: String,
+    /// The message plus any structured fields of the event
+    #[serde(default)]
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Log files in `dir`, newest first. Daily files are named
+/// `inchbox.YYYY-MM-DD.log`, so names sort by date.
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy())
+                .is_some_and(|n| n.starts_with(LOG_PREFIX) && n.ends_with(LOG_SUFFIX))
+        })
+        .collect();
+    files.sort();
+    files.reverse();
+    files
+}
+
+fn level_rank(level: &str) -> u8 {
+    match level.to_ascii_uppercase().as_str() {
+        "ERROR" => 4,
+        "WARN" => 3,
+        "INFO" => 2,
+        "DEBUG" => 1,
+        _ => 0,
+    }
+}
+
+/// The newest `limit` entries at `min_level` or above, oldest first. Lines
+/// that don't parse (e.g. cut off by a crash) are skipped.
+pub fn recent(dir: &Path, limit: usize, min_level: Option<&str>) -> Vec<LogEntry> {
+    let min_rank = min_level.map_or(0, level_rank);
+    let mut entries: Vec<LogEntry> = Vec::new();
+    for file in log_files(dir) {
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let mut from_file: Vec<LogEntry> = content
+            .lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str::<LogEntry>(line).ok())
+            .filter(|entry| level_rank(&entry.level) >= min_rank)
+            .take(limit - entries.len())
+            .collect();
+        // Older files come later, so their entries go in front
+        from_file.reverse();
+        from_file.append(&mut entries);
+        entries = from_file;
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    entries
+}
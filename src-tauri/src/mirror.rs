@@ -0,0 +1,126 @@
+//! Tag changes written where other applications read them: XMP sidecars
+//! and the `user.xdg.tags` attribute. Commands only work out what changed;
+//! a thread of its own does the disk I/O, in the order the changes were
+//! made, so an attach followed by a detach can't land the other way round.
+
+use crate::models::AppData;
+use crate::settings::Settings;
+use crate::{xdg_tags, xmp};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+/// Tag paths (root first) of each file before a change, by file id
+pub type TagPathSnapshot = HashMap<String, Vec<Vec<String>>>;
+
+/// How the tag paths of one file changed
+struct TagChange {
+    path: PathBuf,
+    mime_type: String,
+    removed: Vec<Vec<String>>,
+    added: Vec<Vec<String>>,
+}
+
+struct Job {
+    xmp: bool,
+    xattr: bool,
+    changes: Vec<TagChange>,
+}
+
+pub struct TagMirror {
+    sender: Sender<Job>,
+}
+
+impl TagMirror {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            for job in receiver {
+                write(job);
+            }
+        });
+        Self { sender }
+    }
+
+    /// Tag paths of `file_ids` before a change, for [`TagMirror::submit`]
+    /// to diff against. Empty when nothing is mirrored.
+    pub fn snapshot(
+        data: &AppData,
+        settings: &Settings,
+        file_ids: &HashSet<String>,
+    ) -> TagPathSnapshot {
+        if !settings.xmp_sync && !settings.xattr_tags {
+            return HashMap::new();
+        }
+        data.files
+            .iter()
+            .filter(|f| file_ids.contains(&f.id))
+            .map(|f| (f.id.clone(), data.file_tag_paths(f)))
+            .collect()
+    }
+
+    /// Queue writing how the tags of the files in `before` changed, as
+    /// enabled in the settings. Only the difference goes out, so tags and
+    /// keywords set elsewhere survive. Failures are only logged, the
+    /// library stays the source of truth.
+    pub fn submit(&self, data: &AppData, settings: &Settings, before: TagPathSnapshot) {
+        if !settings.xmp_sync && !settings.xattr_tags {
+            return;
+        }
+        let changes: Vec<TagChange> = data
+            .files
+            .iter()
+            .filter_map(|file| {
+                let old = before.get(&file.id)?;
+                let new = data.file_tag_paths(file);
+                let removed: Vec<Vec<String>> =
+                    old.iter().filter(|p| !new.contains(p)).cloned().collect();
+                let added: Vec<Vec<String>> =
+                    new.iter().filter(|p| !old.contains(p)).cloned().collect();
+                (!removed.is_empty() || !added.is_empty()).then(|| TagChange {
+                    path: PathBuf::from(&file.path),
+                    mime_type: file.mime_type.clone(),
+                    removed,
+                    added,
+                })
+            })
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+        let job = Job {
+            xmp: settings.xmp_sync,
+            xattr: settings.xattr_tags,
+            changes,
+        };
+        if self.sender.send(job).is_err() {
+            tracing::error!("Tag mirror thread is gone, tag changes not written");
+        }
+    }
+}
+
+impl Default for TagMirror {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn write(job: Job) {
+    for change in job.changes {
+        let path = change.path.as_path();
+        if !path.exists() {
+            continue;
+        }
+        if job.xattr {
+            if let Err(e) = xdg_tags::update(path, &change.removed, &change.added) {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to write tag attribute");
+            }
+        }
+        if job.xmp && change.mime_type != "inode/directory" {
+            if let Err(e) = xmp::update(path, &change.mime_type, &change.removed, &change.added) {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to write XMP sidecar");
+            }
+        }
+    }
+}
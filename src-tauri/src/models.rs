@@ -1,13 +1,17 @@
+use crate::thumbnail::placeholder::Placeholder;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 use tauri::Manager;
 use uuid::Uuid;
 
 // Define the current version of the data schema
-const CURRENT_VERSION: u32 = 2;
+pub const CURRENT_VERSION: u32 = 2;
 
 fn default_version() -> u32 {
     CURRENT_VERSION
@@ -41,6 +45,19 @@ pub struct FileItem {
     pub added_at: i64,
     #[serde(default)]
     pub tag_ids: Vec<String>, // Changed from 'tags' (strings) to 'tag_ids' (uuids)
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>, // Filled by extractor::ExtractorRegistry
+    #[serde(default)]
+    pub placeholder: Option<Placeholder>, // Set once a thumbnail has been generated
+}
+
+/// An app version that opened the library, for diagnostics.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VersionRecord {
+    pub app_version: String,
+    pub schema_version: u32,
+    /// Unix millis of the first launch with this version
+    pub first_opened_at: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -50,6 +67,136 @@ pub struct AppData {
     pub files: Vec<FileItem>,
     pub tags: Vec<Tag>,
     pub groups: Vec<TagGroup>,
+    #[serde(default)]
+    pub version_history: Vec<VersionRecord>,
+}
+
+impl AppData {
+    /// Note `app_version` in the history unless it is already the latest
+    /// entry. Returns whether anything changed.
+    pub fn record_app_version(&mut self, app_version: &str, now: i64) -> bool {
+        if self
+            .version_history
+            .last()
+            .is_some_and(|r| r.app_version == app_version)
+        {
+            return false;
+        }
+        self.version_history.push(VersionRecord {
+            app_version: app_version.to_string(),
+            schema_version: self.version,
+            first_opened_at: now,
+        });
+        true
+    }
+
+    /// Names of the tag and its ancestors, root first.
+    pub fn tag_path(&self, tag_id: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut current = self.tags.iter().find(|t| t.id == tag_id);
+        while let Some(tag) = current {
+            // A cycle can only come from a hand-edited library
+            if names.len() > self.tags.len() {
+                break;
+            }
+            names.push(tag.name.clone());
+            current = tag
+                .parent_id
+                .as_ref()
+                .and_then(|id| self.tags.iter().find(|t| &t.id == id));
+        }
+        names.reverse();
+        names
+    }
+
+    /// Paths (root first) of the tags attached to `file`.
+    pub fn file_tag_paths(&self, file: &FileItem) -> Vec<Vec<String>> {
+        file.tag_ids
+            .iter()
+            .map(|id| self.tag_path(id))
+            .filter(|p| !p.is_empty())
+            .collect()
+    }
+
+    /// Id of the tag at `path` (root first), creating the levels that don't
+    /// exist yet into `created`. Each level is looked up under the one
+    /// before; as tag names are unique, a name that already sits elsewhere
+    /// in the hierarchy is a conflict and nothing is created. A single flat
+    /// name, as most applications write keywords, matches its tag wherever
+    /// it sits. `None` for a path without names.
+    pub fn ensure_tag_path(
+        &mut self,
+        path: &[String],
+        created: &mut Vec<Tag>,
+    ) -> Result<Option<String>, TagPathConflict> {
+        let names: Vec<&str> = path
+            .iter()
+            .map(|n| n.trim())
+            .filter(|n| !n.is_empty())
+            .collect();
+        let conflict = |existing: Vec<String>| TagPathConflict {
+            path: path.to_vec(),
+            existing,
+        };
+
+        if let [name] = names[..] {
+            let mut matching = self
+                .tags
+                .iter()
+                .filter(|t| t.name.eq_ignore_ascii_case(name));
+            if let (Some(tag), None) = (matching.next(), matching.next()) {
+                return Ok(Some(tag.id.clone()));
+            }
+        }
+
+        // The levels that exist, each under the one before
+        let mut parent_id: Option<String> = None;
+        let mut depth = 0;
+        for name in &names {
+            let Some(tag) = self.tags.iter().find(|t| t.name.eq_ignore_ascii_case(name)) else {
+                break;
+            };
+            if tag.parent_id != parent_id {
+                return Err(conflict(self.tag_path(&tag.id)));
+            }
+            parent_id = Some(tag.id.clone());
+            depth += 1;
+        }
+        // The rest must be new names, also within the path itself
+        let missing = &names[depth..];
+        for (i, name) in missing.iter().enumerate() {
+            if let Some(tag) = self.tags.iter().find(|t| t.name.eq_ignore_ascii_case(name)) {
+                return Err(conflict(self.tag_path(&tag.id)));
+            }
+            if missing[..i].iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                return Err(conflict(path.to_vec()));
+            }
+        }
+
+        for name in missing {
+            let tag = Tag {
+                id: Uuid::new_v4().to_string(),
+                name: name.to_string(),
+                parent_id: parent_id.clone(),
+                group_id: None,
+            };
+            parent_id = Some(tag.id.clone());
+            self.tags.push(tag.clone());
+            created.push(tag);
+        }
+        Ok(parent_id)
+    }
+}
+
+/// A tag path from another application that can't be mapped onto the
+/// library's tags: one of its names already sits elsewhere in the
+/// hierarchy, and tag names are unique.
+#[derive(Debug, Serialize, Clone)]
+pub struct TagPathConflict {
+    /// The path as the other application stored it
+    pub path: Vec<String>,
+    /// Where the library has the name
+    pub existing: Vec<String>,
 }
 
 impl Default for AppData {
@@ -59,6 +206,7 @@ impl Default for AppData {
             files: vec![],
             tags: vec![],
             groups: vec![],
+            version_history: vec![],
         }
     }
 }
@@ -122,12 +270,50 @@ struct LegacyAppData {
     files: Vec<LegacyFileItem>,
 }
 
+/// How long changes marked with [`AppState::mark_dirty`] wait to be saved
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
 pub struct AppState {
     pub data: Arc<Mutex<AppData>>,
     pub file_path: PathBuf,
+    /// Changes not saved yet, written by the flush thread
+    dirty: Arc<AtomicBool>,
 }
 
 impl AppState {
+    fn with_data(data: AppData, file_path: PathBuf) -> Self {
+        let state = Self {
+            data: Arc::new(Mutex::new(data)),
+            file_path,
+            dirty: Arc::new(AtomicBool::new(false)),
+        };
+        let (data, file_path, dirty) = (
+            state.data.clone(),
+            state.file_path.clone(),
+            state.dirty.clone(),
+        );
+        thread::spawn(move || loop {
+            thread::sleep(FLUSH_INTERVAL);
+            if dirty.swap(false, Ordering::AcqRel) {
+                crate::save_to_disk(&data.lock().unwrap(), &file_path);
+            }
+        });
+        state
+    }
+
+    /// Save the library soon instead of right away, for frequent changes
+    /// nobody waits on, like placeholders arriving with thumbnails.
+    pub fn mark_dirty(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    /// Write pending changes immediately, e.g. before the app exits.
+    pub fn flush(&self) {
+        if self.dirty.swap(false, Ordering::AcqRel) {
+            crate::save_to_disk(&self.data.lock().unwrap(), &self.file_path);
+        }
+    }
+
     pub fn new(app_handle: &tauri::AppHandle) -> Self {
         let app_data_dir = app_handle
             .path()
@@ -176,7 +362,10 @@ impl AppState {
                             files: new_files,
                         }
                     }
-                    Err(_) => AppDataV1::default(),
+                    Err(e) => {
+                        tracing::error!(error = %e, "Failed to parse legacy library, starting empty");
+                        AppDataV1::default()
+                    }
                 }
             } else {
                 // Try parsing as V1 (or V2 if version matches, but here we assume V1 check first)
@@ -189,23 +378,28 @@ impl AppState {
                         if v >= 2 {
                             // It's V2 or higher, parse directly as AppData
                             match serde_json::from_str::<AppData>(&content) {
-                                Ok(data) => {
-                                    return Self {
-                                        data: Arc::new(Mutex::new(data)),
-                                        file_path,
-                                    }
+                                Ok(data) => return Self::with_data(data, file_path),
+                                Err(e) => {
+                                    tracing::error!(
+                                        version = v,
+                                        error = %e,
+                                        "Failed to parse library, starting empty"
+                                    );
+                                    AppDataV1::default()
                                 }
-                                Err(_) => AppDataV1::default(), // Fallback
                             }
                         } else {
                             // Version 1
+                            tracing::info!(version = v, "Migrating library to version 2");
                             serde_json::from_str::<AppDataV1>(&content).unwrap_or_default()
                         }
                     } else {
                         // Missing version, assume V1
+                        tracing::info!("Migrating unversioned library to version 2");
                         serde_json::from_str::<AppDataV1>(&content).unwrap_or_default()
                     }
                 } else {
+                    tracing::error!("Library file is not valid JSON, starting empty");
                     AppDataV1::default()
                 }
             };
@@ -255,6 +449,8 @@ impl AppState {
                         mime_type: f.mime_type,
                         added_at: f.added_at,
                         tag_ids,
+                        metadata: HashMap::new(),
+                        placeholder: None,
                     }
                 })
                 .collect();
@@ -264,20 +460,82 @@ impl AppState {
                 files: files_v2,
                 tags: tags_v2,
                 groups: vec![],
+                version_history: vec![],
             };
 
             // Save migrated data
             let save_content = serde_json::to_string_pretty(&final_data).unwrap();
-            let _ = fs::write(&file_path, save_content);
+            if let Err(e) = fs::write(&file_path, save_content) {
+                tracing::error!(error = %e, "Failed to save migrated library");
+            }
+            tracing::info!(
+                files = final_data.files.len(),
+                tags = final_data.tags.len(),
+                "Library migrated to version 2"
+            );
 
             final_data
         } else {
             AppData::default()
         };
 
-        Self {
-            data: Arc::new(Mutex::new(final_data)),
-            file_path,
-        }
+        Self::with_data(final_data, file_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(levels: &[&str]) -> Vec<String> {
+        levels.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn ensures_nested_paths_level_by_level() {
+        let mut data = AppData::default();
+        let mut created = Vec::new();
+        let paris = data
+            .ensure_tag_path(&path(&["Travel", "Paris"]), &mut created)
+            .unwrap()
+            .unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(data.tag_path(&paris), path(&["Travel", "Paris"]));
+
+        // The same path again creates nothing
+        created.clear();
+        let again = data
+            .ensure_tag_path(&path(&["travel", "paris"]), &mut created)
+            .unwrap();
+        assert_eq!(again, Some(paris));
+        assert!(created.is_empty());
+
+        // Paris already sits under Travel, not Texas
+        let conflict = data
+            .ensure_tag_path(&path(&["Texas", "Paris"]), &mut created)
+            .unwrap_err();
+        assert_eq!(conflict.existing, path(&["Travel", "Paris"]));
+        assert!(created.is_empty());
+    }
+
+    #[test]
+    fn matches_a_flat_name_to_its_nested_tag() {
+        let mut data = AppData::default();
+        let mut created = Vec::new();
+        let paris = data
+            .ensure_tag_path(&path(&["Travel", "Europe", "Paris"]), &mut created)
+            .unwrap();
+        created.clear();
+
+        assert_eq!(
+            data.ensure_tag_path(&path(&["Paris"]), &mut created)
+                .unwrap(),
+            paris
+        );
+        assert!(created.is_empty());
+        assert_eq!(
+            data.ensure_tag_path(&path(&["  "]), &mut created).unwrap(),
+            None
+        );
     }
 }
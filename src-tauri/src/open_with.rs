@@ -0,0 +1,582 @@
+use crate::error::InchBoxError;
+use serde::Serialize;
+use std::path::Path;
+
+/// An application that can open a given file.
+#[derive(Debug, Serialize, Clone)]
+pub struct AppInfo {
+    /// Desktop file id on Linux (`org.gnome.Evince.desktop`), ProgID or
+    /// executable name on Windows
+    pub id: String,
+    pub name: String,
+    /// The system's default handler for this type
+    pub is_default: bool,
+    /// Chosen by the user in InchBox for this extension
+    pub is_preferred: bool,
+}
+
+/// Applications registered for the file's type, the system default first.
+pub fn apps_for(path: &Path, mime_type: &str) -> Vec<AppInfo> {
+    platform::apps_for(path, mime_type)
+}
+
+/// Open `path` with the application `app_id` as returned by [`apps_for`].
+pub fn open_with(path: &Path, app_id: &str) -> Result<(), InchBoxError> {
+    platform::open_with(path, app_id)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::AppInfo;
+    use crate::error::InchBoxError;
+    use crate::i18n::fl;
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+
+    struct DesktopEntry {
+        name: String,
+        exec: String,
+        mime_types: Vec<String>,
+        hidden: bool,
+    }
+
+    pub fn apps_for(_path: &Path, mime_type: &str) -> Vec<AppInfo> {
+        let entries = desktop_entries();
+        let associations = Associations::load();
+
+        let mut ids: Vec<String> = Vec::new();
+        let mut push = |id: &str| {
+            if !ids.iter().any(|i| i == id) {
+                ids.push(id.to_string());
+            }
+        };
+        let default = associations
+            .default_candidates(mime_type)
+            .find(|id| entries.get(id.as_str()).is_some_and(|e| !e.hidden));
+        if let Some(default) = default {
+            push(default);
+        }
+        for id in associations.added.get(mime_type).into_iter().flatten() {
+            push(id);
+        }
+        // Every installed application declaring the type itself
+        let mut declaring: Vec<&String> = entries
+            .iter()
+            .filter(|(_, e)| e.mime_types.iter().any(|m| m == mime_type))
+            .map(|(id, _)| id)
+            .collect();
+        declaring.sort();
+        for id in declaring {
+            push(id);
+        }
+
+        let removed = associations.removed.get(mime_type);
+        ids.into_iter()
+            .filter(|id| !removed.is_some_and(|r| r.contains(id)))
+            .filter_map(|id| {
+                let entry = entries.get(&id).filter(|e| !e.hidden)?;
+                Some(AppInfo {
+                    is_default: default == Some(&id),
+                    name: entry.name.clone(),
+                    id,
+                    is_preferred: false,
+                })
+            })
+            .collect()
+    }
+
+    pub fn open_with(path: &Path, app_id: &str) -> Result<(), InchBoxError> {
+        let entry =
+            desktop_entries()
+                .remove(app_id)
+                .ok_or_else(|| InchBoxError::ApplicationNotFound {
+                    app_id: app_id.to_string(),
+                })?;
+        let mut args = expand_exec(&entry.exec, path).map_err(InchBoxError::platform)?;
+        if args.is_empty() {
+            return Err(InchBoxError::platform(fl!(
+                "error-invalid-exec",
+                app_id = app_id
+            )));
+        }
+        let program = args.remove(0);
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                InchBoxError::platform(fl!(
+                    "error-launch-failed",
+                    app = entry.name.as_str(),
+                    reason = e.to_string()
+                ))
+            })?;
+        Ok(())
+    }
+
+    /// `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`, most important first.
+    fn data_dirs() -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = dirs::data_dir().into_iter().collect();
+        let system = std::env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+        dirs.extend(system.split(':').map(PathBuf::from));
+        dirs
+    }
+
+    fn config_dirs() -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = dirs::config_dir().into_iter().collect();
+        let system = std::env::var("XDG_CONFIG_DIRS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "/etc/xdg".to_string());
+        dirs.extend(system.split(':').map(PathBuf::from));
+        dirs
+    }
+
+    /// Installed applications by desktop file id. Entries in more important
+    /// directories shadow ones with the same id further down.
+    fn desktop_entries() -> HashMap<String, DesktopEntry> {
+        let mut entries = HashMap::new();
+        for dir in data_dirs().iter().rev() {
+            let applications = dir.join("applications");
+            collect_entries(&applications, &applications, &mut entries);
+        }
+        entries
+    }
+
+    fn collect_entries(root: &Path, dir: &Path, entries: &mut HashMap<String, DesktopEntry>) {
+        let Ok(read_dir) = fs::read_dir(dir) else {
+            return;
+        };
+        for path in read_dir.flatten().map(|e| e.path()) {
+            if path.is_dir() {
+                collect_entries(root, &path, entries);
+            } else if path.extension().is_some_and(|e| e == "desktop") {
+                // Subdirectories become part of the id: kde/foo.desktop -> kde-foo.desktop
+                let Ok(relative) = path.strip_prefix(root) else {
+                    continue;
+                };
+                let id = relative.to_string_lossy().replace('/', "-");
+                if let Some(entry) = parse_desktop_entry(&path) {
+                    entries.insert(id, entry);
+                }
+            }
+        }
+    }
+
+    fn parse_desktop_entry(path: &Path) -> Option<DesktopEntry> {
+        let content = fs::read_to_string(path).ok()?;
+        let group = ini_group(&content, "Desktop Entry");
+        if group.get("Type").is_some_and(|t| *t != "Application") {
+            return None;
+        }
+        let flag = |key: &str| group.get(key).is_some_and(|v| *v == "true");
+        Some(DesktopEntry {
+            name: group.get("Name")?.to_string(),
+            exec: group.get("Exec")?.to_string(),
+            mime_types: list_value(group.get("MimeType").copied().unwrap_or("")),
+            hidden: flag("Hidden") || flag("NoDisplay"),
+        })
+    }
+
+    /// Key/value pairs of one `[group]` of an ini-style file. Localized keys
+    /// (`Name[de]`) are skipped.
+    fn ini_group<'a>(content: &'a str, name: &str) -> HashMap<&'a str, &'a str> {
+        let mut values = HashMap::new();
+        let mut in_group = false;
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_group = line == format!("[{}]", name);
+            } else if in_group && !line.starts_with('#') {
+                if let Some((key, value)) = line.split_once('=') {
+                    let key = key.trim();
+                    if !key.contains('[') {
+                        values.entry(key).or_insert(value.trim());
+                    }
+                }
+            }
+        }
+        values
+    }
+
+    fn list_value(value: &str) -> Vec<String> {
+        value
+            .split(';')
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Merged `mimeapps.list` files plus the `mimeinfo.cache` defaults.
+    #[derive(Default)]
+    struct Associations {
+        default: HashMap<String, Vec<String>>,
+        added: HashMap<String, Vec<String>>,
+        removed: HashMap<String, HashSet<String>>,
+        /// Applications by type from `mimeinfo.cache`, in data dir order;
+        /// the default falls back to these when no list names one
+        cached: HashMap<String, Vec<String>>,
+    }
+
+    impl Associations {
+        fn load() -> Self {
+            let mut lists: Vec<PathBuf> = config_dirs()
+                .into_iter()
+                .map(|d| d.join("mimeapps.list"))
+                .collect();
+            lists.extend(
+                data_dirs()
+                    .into_iter()
+                    .map(|d| d.join("applications").join("mimeapps.list")),
+            );
+
+            // Earlier files take precedence, so later ones only append
+            let mut associations = Self::default();
+            for list in lists {
+                let Ok(content) = fs::read_to_string(&list) else {
+                    continue;
+                };
+                for (group, target) in [
+                    ("Default Applications", &mut associations.default),
+                    ("Added Associations", &mut associations.added),
+                ] {
+                    for (mime, apps) in ini_group(&content, group) {
+                        target
+                            .entry(mime.to_string())
+                            .or_default()
+                            .extend(list_value(apps));
+                    }
+                }
+                for (mime, apps) in ini_group(&content, "Removed Associations") {
+                    associations
+                        .removed
+                        .entry(mime.to_string())
+                        .or_default()
+                        .extend(list_value(apps));
+                }
+            }
+
+            for dir in data_dirs() {
+                let cache = dir.join("applications").join("mimeinfo.cache");
+                let Ok(content) = fs::read_to_string(&cache) else {
+                    continue;
+                };
+                for (mime, apps) in ini_group(&content, "MIME Cache") {
+                    associations
+                        .cached
+                        .entry(mime.to_string())
+                        .or_default()
+                        .extend(list_value(apps));
+                }
+            }
+            associations
+        }
+
+        /// Candidates for the default application of `mime_type`, most
+        /// preferred first: the default lists, then added associations,
+        /// then what `mimeinfo.cache` names, minus removed associations.
+        fn default_candidates<'a>(&'a self, mime_type: &str) -> impl Iterator<Item = &'a String> {
+            let removed = self.removed.get(mime_type);
+            let lists: Vec<&Vec<String>> = [&self.default, &self.added, &self.cached]
+                .into_iter()
+                .filter_map(|map| map.get(mime_type))
+                .collect();
+            lists
+                .into_iter()
+                .flatten()
+                .filter(move |id| !removed.is_some_and(|r| r.contains(*id)))
+        }
+    }
+
+    /// Split a desktop entry `Exec` value into arguments and substitute the
+    /// field codes for `path`, also inside arguments like `--open=%u`.
+    /// Without a file field code the path is appended.
+    fn expand_exec(exec: &str, path: &Path) -> Result<Vec<String>, String> {
+        let file = path.to_string_lossy().to_string();
+        let uri = crate::thumbnail::freedesktop::file_uri(path).unwrap_or_else(|| file.clone());
+
+        let mut args = Vec::new();
+        let mut has_file_code = false;
+        for token in split_exec(exec)? {
+            let mut arg = String::new();
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    arg.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('%') => arg.push('%'),
+                    Some('f' | 'F') => {
+                        arg.push_str(&file);
+                        has_file_code = true;
+                    }
+                    Some('u' | 'U') => {
+                        arg.push_str(&uri);
+                        has_file_code = true;
+                    }
+                    // Icon, translated name and desktop file location are
+                    // not passed; %d %D %n %N %v %m are deprecated
+                    _ => {}
+                }
+            }
+            // An argument that was only such a code goes away entirely
+            if arg.is_empty() && !token.is_empty() {
+                continue;
+            }
+            args.push(arg);
+        }
+        if !has_file_code {
+            args.push(file);
+        }
+        Ok(args)
+    }
+
+    /// Tokenize following the desktop entry quoting rules: arguments may be
+    /// double-quoted, with `\` escaping `"`, `` ` ``, `$` and `\` inside.
+    fn split_exec(exec: &str) -> Result<Vec<String>, String> {
+        let mut tokens = Vec::new();
+        let mut current = String::new();
+        let mut in_quotes = false;
+        let mut has_token = false;
+        let mut chars = exec.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    in_quotes = !in_quotes;
+                    has_token = true;
+                }
+                '\\' if in_quotes => {
+                    if let Some(escaped) = chars.next() {
+                        current.push(escaped);
+                    }
+                }
+                c if c.is_whitespace() && !in_quotes => {
+                    if has_token {
+                        tokens.push(std::mem::take(&mut current));
+                        has_token = false;
+                    }
+                }
+                c => {
+                    current.push(c);
+                    has_token = true;
+                }
+            }
+        }
+        if in_quotes {
+            return Err(fl!("error-invalid-exec-quote"));
+        }
+        if has_token {
+            tokens.push(current);
+        }
+        Ok(tokens)
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::AppInfo;
+    use crate::error::InchBoxError;
+    use crate::i18n::fl;
+    use std::os::windows::process::CommandExt;
+    use std::path::Path;
+    use std::process::Command;
+    use winreg::enums::{HKEY_CLASSES_ROOT, HKEY_CURRENT_USER};
+    use winreg::RegKey;
+
+    const FILE_EXTS: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer\FileExts";
+
+    pub fn apps_for(path: &Path, _mime_type: &str) -> Vec<AppInfo> {
+        let Some(ext) = dotted_extension(path) else {
+            return Vec::new();
+        };
+        let classes = RegKey::predef(HKEY_CLASSES_ROOT);
+        let user_exts = RegKey::predef(HKEY_CURRENT_USER)
+            .open_subkey(format!(r"{}\{}", FILE_EXTS, ext))
+            .ok();
+
+        // The user's choice in Explorer wins over the class default
+        let default = user_exts
+            .as_ref()
+            .and_then(|k| k.open_subkey("UserChoice").ok())
+            .and_then(|k| k.get_value::<String, _>("ProgId").ok())
+            .or_else(|| {
+                classes
+                    .open_subkey(&ext)
+                    .ok()?
+                    .get_value::<String, _>("")
+                    .ok()
+            });
+
+        let mut ids: Vec<String> = default.iter().cloned().collect();
+        let mut push = |id: String| {
+            if !id.is_empty() && !ids.iter().any(|i| i.eq_ignore_ascii_case(&id)) {
+                ids.push(id);
+            }
+        };
+        // ProgIDs are listed as value names, applications as subkeys or values
+        for key in [
+            classes
+                .open_subkey(format!(r"{}\OpenWithProgids", ext))
+                .ok(),
+            user_exts
+                .as_ref()
+                .and_then(|k| k.open_subkey("OpenWithProgids").ok()),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            key.enum_values().flatten().for_each(|(name, _)| push(name));
+        }
+        if let Ok(key) = classes.open_subkey(format!(r"{}\OpenWithList", ext)) {
+            key.enum_keys().flatten().for_each(&mut push);
+        }
+        if let Some(key) = user_exts
+            .as_ref()
+            .and_then(|k| k.open_subkey("OpenWithList").ok())
+        {
+            key.enum_values()
+                .flatten()
+                .filter(|(name, _)| name != "MRUList")
+                .map(|(_, value)| value.to_string())
+                .for_each(&mut push);
+        }
+
+        ids.into_iter()
+            .filter_map(|id| {
+                let (name, _) = resolve(&classes, &id)?;
+                Some(AppInfo {
+                    is_default: default.as_deref() == Some(id.as_str()),
+                    name,
+                    id,
+                    is_preferred: false,
+                })
+            })
+            .collect()
+    }
+
+    pub fn open_with(path: &Path, app_id: &str) -> Result<(), InchBoxError> {
+        let classes = RegKey::predef(HKEY_CLASSES_ROOT);
+        let (name, command) =
+            resolve(&classes, app_id).ok_or_else(|| InchBoxError::ApplicationNotFound {
+                app_id: app_id.to_string(),
+            })?;
+
+        let file = path.to_string_lossy();
+        let (program, args) = split_program(&command);
+        let args = if args.contains("%1") || args.contains("%L") || args.contains("%l") {
+            args.replace("%1", &file)
+                .replace("%L", &file)
+                .replace("%l", &file)
+        } else {
+            format!("{} \"{}\"", args, file)
+        };
+        // Commands are registered as raw command lines with their own quoting
+        Command::new(program)
+            .raw_arg(args.replace("%*", ""))
+            .spawn()
+            .map_err(|e| {
+                InchBoxError::platform(fl!(
+                    "error-launch-failed",
+                    app = name.as_str(),
+                    reason = e.to_string()
+                ))
+            })?;
+        Ok(())
+    }
+
+    fn dotted_extension(path: &Path) -> Option<String> {
+        path.extension()
+            .map(|e| format!(".{}", e.to_string_lossy().to_lowercase()))
+    }
+
+    /// Display name and open command of a ProgID or an `Applications\<exe>`
+    /// registration.
+    fn resolve(classes: &RegKey, id: &str) -> Option<(String, String)> {
+        let key = if id.to_lowercase().ends_with(".exe") {
+            classes.open_subkey(format!(r"Applications\{}", id)).ok()?
+        } else {
+            classes.open_subkey(id).ok()?
+        };
+        let command: String = key
+            .open_subkey(r"shell\open\command")
+            .ok()?
+            .get_value("")
+            .ok()?;
+        let command = expand_env(&command);
+
+        let name = key
+            .get_value::<String, _>("FriendlyAppName")
+            .ok()
+            .or_else(|| key.get_value::<String, _>("").ok())
+            .filter(|n| !n.is_empty() && !n.starts_with('@'))
+            .unwrap_or_else(|| {
+                let (program, _) = split_program(&command);
+                Path::new(&program)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| id.to_string())
+            });
+        Some((name, command))
+    }
+
+    /// Split a registered command line into the program and the raw rest.
+    fn split_program(command: &str) -> (String, String) {
+        let command = command.trim();
+        if let Some(rest) = command.strip_prefix('"') {
+            if let Some((program, args)) = rest.split_once('"') {
+                return (program.to_string(), args.trim().to_string());
+            }
+        }
+        match command.split_once(' ') {
+            Some((program, args)) => (program.to_string(), args.trim().to_string()),
+            None => (command.to_string(), String::new()),
+        }
+    }
+
+    /// Expand `%VAR%` references of REG_EXPAND_SZ values.
+    fn expand_env(value: &str) -> String {
+        let mut result = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find('%') {
+            let Some(len) = rest[start + 1..].find('%') else {
+                break;
+            };
+            let name = &rest[start + 1..start + 1 + len];
+            match std::env::var(name) {
+                Ok(expanded) if !name.is_empty() => {
+                    result.push_str(&rest[..start]);
+                    result.push_str(&expanded);
+                }
+                // Not a variable (e.g. %1), keep it as is
+                _ => result.push_str(&rest[..start + len + 2]),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+mod platform {
+    use super::AppInfo;
+    use crate::error::InchBoxError;
+    use crate::i18n::fl;
+    use std::path::Path;
+
+    pub fn apps_for(_path: &Path, _mime_type: &str) -> Vec<AppInfo> {
+        Vec::new()
+    }
+
+    pub fn open_with(_path: &Path, _app_id: &str) -> Result<(), InchBoxError> {
+        Err(InchBoxError::platform(fl!("error-not-supported")))
+    }
+}
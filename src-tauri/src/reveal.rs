@@ -0,0 +1,158 @@
+use crate::i18n::fl;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Show `paths` in the system file manager with the items selected. Paths
+/// in the same folder are revealed together in one window.
+pub async fn reveal(paths: Vec<PathBuf>) -> Result<(), String> {
+    if paths.is_empty() {
+        return Err(fl!("error-no-files"));
+    }
+    platform::reveal(paths).await
+}
+
+/// Group paths by the folder containing them.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+fn by_parent(paths: &[PathBuf]) -> BTreeMap<PathBuf, Vec<PathBuf>> {
+    let mut groups: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+    for path in paths {
+        let parent = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        groups.entry(parent).or_default().push(path.clone());
+    }
+    groups
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use crate::i18n::fl;
+    use std::path::PathBuf;
+    use windows::core::HSTRING;
+    use windows::Win32::System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED};
+    use windows::Win32::UI::Shell::Common::ITEMIDLIST;
+    use windows::Win32::UI::Shell::{ILCreateFromPathW, ILFree, SHOpenFolderAndSelectItems};
+
+    /// The shell call is synchronous COM, so it runs on the blocking pool
+    pub async fn reveal(paths: Vec<PathBuf>) -> Result<(), String> {
+        tauri::async_runtime::spawn_blocking(move || reveal_blocking(&paths))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    fn reveal_blocking(paths: &[PathBuf]) -> Result<(), String> {
+        for (folder, items) in super::by_parent(paths) {
+            unsafe {
+                let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+
+                let folder_pidl = ILCreateFromPathW(&HSTRING::from(folder.as_os_str()));
+                if folder_pidl.is_null() {
+                    return Err(fl!(
+                        "error-path-not-found",
+                        path = folder.display().to_string()
+                    ));
+                }
+                let mut item_pidls: Vec<*const ITEMIDLIST> = Vec::with_capacity(items.len());
+                let mut missing: Vec<String> = Vec::new();
+                for item in &items {
+                    let pidl = ILCreateFromPathW(&HSTRING::from(item.as_os_str()));
+                    if pidl.is_null() {
+                        missing.push(item.display().to_string());
+                    } else {
+                        item_pidls.push(pidl as *const _);
+                    }
+                }
+
+                let result = if missing.is_empty() {
+                    SHOpenFolderAndSelectItems(folder_pidl, Some(&item_pidls), 0)
+                        .map_err(|e| fl!("error-reveal-failed", reason = e.to_string()))
+                } else {
+                    Err(fl!("error-path-not-found", path = missing.join(", ")))
+                };
+
+                for pidl in item_pidls {
+                    ILFree(Some(pidl));
+                }
+                ILFree(Some(folder_pidl as *const _));
+                result?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use crate::i18n::fl;
+    use crate::thumbnail::freedesktop::file_uri;
+    use std::path::PathBuf;
+
+    pub async fn reveal(paths: Vec<PathBuf>) -> Result<(), String> {
+        match show_items(&paths).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                // No file manager implements the interface (or no session
+                // bus); at least open the folders
+                tracing::warn!(error = %e, "FileManager1.ShowItems failed, opening folders");
+                for folder in super::by_parent(&paths).into_keys() {
+                    open::that_detached(&folder).map_err(|e| e.to_string())?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Ask the desktop's file manager to reveal the items via
+    /// `org.freedesktop.FileManager1`, supported by Nautilus, Dolphin,
+    /// Nemo, Thunar and others. The call is awaited so a file manager that
+    /// is slow to start never holds up a thread.
+    async fn show_items(paths: &[PathBuf]) -> Result<(), String> {
+        let uris: Vec<String> = paths
+            .iter()
+            .map(|p| {
+                file_uri(p)
+                    .ok_or_else(|| fl!("error-path-not-found", path = p.display().to_string()))
+            })
+            .collect::<Result<_, _>>()?;
+        let connection = zbus::Connection::session()
+            .await
+            .map_err(|e| e.to_string())?;
+        connection
+            .call_method(
+                Some("org.freedesktop.FileManager1"),
+                "/org/freedesktop/FileManager1",
+                Some("org.freedesktop.FileManager1"),
+                "ShowItems",
+                // The second argument is the startup notification id
+                &(uris, ""),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::path::PathBuf;
+    use std::process::Command;
+
+    pub async fn reveal(paths: Vec<PathBuf>) -> Result<(), String> {
+        Command::new("open")
+            .arg("-R")
+            .args(paths)
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+mod platform {
+    use std::path::PathBuf;
+
+    pub async fn reveal(paths: Vec<PathBuf>) -> Result<(), String> {
+        for folder in super::by_parent(&paths).into_keys() {
+            open::that_detached(&folder).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// Reuse thumbnails other applications stored in `~/.cache/thumbnails` (Linux)
+    pub read_freedesktop_thumbnails: bool,
+    /// Also store our thumbnails in `~/.cache/thumbnails` (Linux)
+    pub write_freedesktop_thumbnails: bool,
+    /// Upper bound for the thumbnail cache before old entries are evicted
+    pub max_thumbnail_cache_mb: u64,
+    /// pdfium library, or the directory holding it; read once at startup
+    pub pdfium_library_path: Option<String>,
+    /// Application chosen in "Open with" by lowercase extension, used
+    /// instead of the system default
+    pub preferred_apps: HashMap<String, String>,
+    /// Backend language (tray, messages); `None` follows the desktop locale
+    pub language: Option<String>,
+    /// Keep tags in XMP sidecars (`dc:subject`, `lr:hierarchicalSubject`)
+    /// and pick up keywords from them and from embedded XMP when adding files
+    pub xmp_sync: bool,
+    /// Mirror tag names into the `user.xdg.tags` extended attribute and
+    /// pick them up from it when adding files (Linux)
+    pub xattr_tags: bool,
+}
+
+impl Settings {
+    pub fn max_thumbnail_cache_bytes(&self) -> u64 {
+        self.max_thumbnail_cache_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn preferred_app(&self, path: &Path) -> Option<&String> {
+        self.preferred_apps.get(&extension_key(path)?)
+    }
+}
+
+/// Settings changed from the settings view. Fields left out keep their
+/// current value, so values saved elsewhere meanwhile (language, preferred
+/// apps) are never overwritten by a stale copy.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct SettingsPatch {
+    pub read_freedesktop_thumbnails: Option<bool>,
+    pub write_freedesktop_thumbnails: Option<bool>,
+    pub max_thumbnail_cache_mb: Option<u64>,
+    pub xmp_sync: Option<bool>,
+    pub xattr_tags: Option<bool>,
+}
+
+impl SettingsPatch {
+    pub fn apply(self, settings: &mut Settings) {
+        if let Some(value) = self.read_freedesktop_thumbnails {
+            settings.read_freedesktop_thumbnails = value;
+        }
+        if let Some(value) = self.write_freedesktop_thumbnails {
+            settings.write_freedesktop_thumbnails = value;
+        }
+        if let Some(value) = self.max_thumbnail_cache_mb {
+            settings.max_thumbnail_cache_mb = value;
+        }
+        if let Some(value) = self.xmp_sync {
+            settings.xmp_sync = value;
+        }
+        if let Some(value) = self.xattr_tags {
+            settings.xattr_tags = value;
+        }
+    }
+}
+
+/// Key of `preferred_apps` for a file.
+pub fn extension_key(path: &Path) -> Option<String> {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase())
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            read_freedesktop_thumbnails: true,
+            write_freedesktop_thumbnails: false,
+            max_thumbnail_cache_mb: 512,
+            pdfium_library_path: None,
+            preferred_apps: HashMap::new(),
+            language: None,
+            xmp_sync: false,
+            xattr_tags: false,
+        }
+    }
+}
+
+pub struct SettingsState {
+    pub data: Arc<Mutex<Settings>>,
+    pub file_path: PathBuf,
+}
+
+impl SettingsState {
+    pub fn new(app_handle: &tauri::AppHandle) -> Self {
+        let app_data_dir = app_handle
+            .path()
+            .app_data_dir()
+            .unwrap_or_else(|_| PathBuf::from("."));
+        if !app_data_dir.exists() {
+            let _ = fs::create_dir_all(&app_data_dir);
+        }
+        let file_path = app_data_dir.join("settings.json");
+
+        // Unknown or missing fields fall back to their defaults
+        let settings = fs::read_to_string(&file_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Settings>(&content).ok())
+            .unwrap_or_default();
+
+        Self {
+            data: Arc::new(Mutex::new(settings)),
+            file_path,
+        }
+    }
+
+    /// Snapshot of the current settings without holding the lock.
+    pub fn get(&self) -> Settings {
+        self.data.lock().unwrap().clone()
+    }
+
+    pub fn save(&self, settings: &Settings) {
+        if let Ok(content) = serde_json::to_string_pretty(settings) {
+            let _ = fs::write(&self.file_path, content);
+        }
+    }
+}
@@ -1,200 +1,166 @@
-use image::{ImageBuffer, Rgba};
-use pdfium_render::prelude::*;
-use std::path::Path;
-#[cfg(target_os = "windows")]
-use windows::{
-    core::*, Win32::Foundation::*, Win32::Graphics::Gdi::*, Win32::System::Com::*,
-    Win32::UI::Shell::*,
-};
-
-pub fn generate_thumbnail(
-    input_path: &Path,
-    output_path: &Path,
-    max_size: u32,
-) -> std::result::Result<(), String> {
-    let extension = input_path
-        .extension()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_lowercase();
-
-    match extension.as_str() {
-        "pdf" => generate_pdf_thumbnail(input_path, output_path, max_size),
-        _ => generate_system_thumbnail(input_path, output_path, max_size),
-    }
+pub mod audio;
+pub mod document;
+#[cfg(target_os = "linux")]
+pub mod freedesktop;
+pub mod pdf;
+pub mod placeholder;
+pub mod queue;
+pub mod raster;
+pub mod raw;
+#[cfg(all(target_os = "windows", feature = "shell-thumbnails"))]
+pub mod shell;
+pub mod svg;
+pub mod video;
+
+use crate::extractor::ExtractorRegistry;
+use crate::i18n::fl;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::DynamicImage;
+use mime_guess::from_path;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Size tiers the frontend can request, by longest edge in pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+    XLarge,
 }
 
-fn generate_pdf_thumbnail(
-    input_path: &Path,
-    output_path: &Path,
-    max_size: u32,
-) -> std::result::Result<(), String> {
-    // Initialize Pdfium
-    let pdfium = Pdfium::new(
-        Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("./"))
-            .or_else(|_| Pdfium::bind_to_system_library())
-            .map_err(|e| format!("Failed to bind to Pdfium: {}", e))?,
-    );
-
-    let document = pdfium
-        .load_pdf_from_file(input_path, None)
-        .map_err(|e| format!("Failed to load PDF: {}", e))?;
-
-    let page = document
-        .pages()
-        .get(0)
-        .map_err(|e| format!("Failed to get first page: {}", e))?;
-
-    // Render page to bitmap
-    let bitmap = page
-        .render_with_config(
-            &PdfRenderConfig::new()
-                .set_target_width((max_size as u16).into())
-                .set_target_height((max_size as u16).into())
-                .rotate_if_landscape(PdfPageRenderRotation::None, true),
-        )
-        .map_err(|e| format!("Failed to render page: {}", e))?;
-
-    // Convert to DynamicImage
-    let img = bitmap.as_image(); // returns DynamicImage directly
-
-    // Save directly (it is already resized by render_with_config)
-    img.save(output_path)
-        .map_err(|e| format!("Failed to save thumbnail: {}", e))
-}
-
-#[cfg(target_os = "windows")]
-fn generate_system_thumbnail(
-    input_path: &Path,
-    output_path: &Path,
-    max_size: u32,
-) -> std::result::Result<(), String> {
-    unsafe {
-        // Initialize COM library on this thread
-        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
-
-        let path_hstring = HSTRING::from(input_path.as_os_str());
-
-        let shell_item: IShellItem = SHCreateItemFromParsingName(&path_hstring, None)
-            .map_err(|e| format!("Failed to create shell item: {}", e))?;
-
-        let image_factory: IShellItemImageFactory = shell_item
-            .cast()
-            .map_err(|e| format!("Failed to cast to image factory: {}", e))?;
-
-        let size = SIZE {
-            cx: max_size as i32,
-            cy: max_size as i32,
-        };
-
-        let hbitmap = image_factory
-            .GetImage(size, SIIGBF_RESIZETOFIT)
-            .map_err(|e| format!("Failed to get image: {}", e))?;
-
-        // Convert HBITMAP to Image
-        let image = hbitmap_to_image(hbitmap)?;
-
-        // Cleanup HBITMAP
-        let _ = DeleteObject(hbitmap);
-
-        // Save
-        image
-            .save(output_path)
-            .map_err(|e| format!("Failed to save image: {}", e))?;
-
-        Ok(())
+impl ThumbnailSize {
+    pub fn pixels(self) -> u32 {
+        match self {
+            ThumbnailSize::Small => 160,
+            ThumbnailSize::Medium => 320,
+            ThumbnailSize::Large => 640,
+            ThumbnailSize::XLarge => 1280,
+        }
     }
 }
 
-#[cfg(not(target_os = "windows"))]
-fn generate_system_thumbnail(
-    _input_path: &Path,
-    _output_path: &Path,
-    _max_size: u32,
-) -> std::result::Result<(), String> {
-    Err("System thumbnail generation not supported on this OS".to_string())
+/// Encoding of a cached thumbnail. Opaque previews are stored as JPEG, ones
+/// with transparency as lossless WebP.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    Png,
+    Webp,
 }
 
-#[cfg(target_os = "windows")]
-unsafe fn hbitmap_to_image(hbitmap: HBITMAP) -> std::result::Result<image::DynamicImage, String> {
-    let hdc = CreateCompatibleDC(None);
-    if hdc.is_invalid() {
-        return Err("Failed to create compatible DC".to_string());
+impl ThumbnailFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Png => "png",
+            ThumbnailFormat::Webp => "webp",
+        }
     }
 
-    let mut bitmap_info = BITMAP {
-        ..Default::default()
-    };
-    if GetObjectW(
-        hbitmap,
-        std::mem::size_of::<BITMAP>() as i32,
-        Some(&mut bitmap_info as *mut _ as *mut _),
-    ) == 0
-    {
-        let _ = DeleteDC(hdc);
-        return Err("Failed to get bitmap info".to_string());
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "jpg" => Some(ThumbnailFormat::Jpeg),
+            "png" => Some(ThumbnailFormat::Png),
+            "webp" => Some(ThumbnailFormat::Webp),
+            _ => None,
+        }
     }
 
-    let width = bitmap_info.bmWidth;
-    let height = bitmap_info.bmHeight;
-    let abs_height = height.abs();
-
-    let mut bmi = BITMAPINFO {
-        bmiHeader: BITMAPINFOHEADER {
-            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
-            biWidth: width,
-            biHeight: -abs_height, // Top-down
-            biPlanes: 1,
-            biBitCount: 32,
-            biCompression: BI_RGB.0, // Use .0 for u32 value
-            ..Default::default()
-        },
-        ..Default::default()
-    };
-
-    let mut pixels: Vec<u8> = vec![0; (width * abs_height * 4) as usize];
-
-    if GetDIBits(
-        hdc,
-        hbitmap,
-        0,
-        abs_height as u32,
-        Some(pixels.as_mut_ptr() as *mut _),
-        &mut bmi,
-        DIB_RGB_COLORS,
-    ) == 0
-    {
-        let _ = DeleteDC(hdc);
-        return Err("Failed to get DIB bits".to_string());
+    fn for_image(img: &DynamicImage) -> Self {
+        let has_transparency =
+            img.color().has_alpha() && img.to_rgba8().pixels().any(|p| p.0[3] < u8::MAX);
+        if has_transparency {
+            ThumbnailFormat::Webp
+        } else {
+            ThumbnailFormat::Jpeg
+        }
     }
+}
 
-    let _ = DeleteDC(hdc);
-
-    // Pixels are BGRA, convert to RGBA
-    for chunk in pixels.chunks_mut(4) {
-        let b = chunk[0];
-        let r = chunk[2];
-        chunk[0] = r;
-        chunk[2] = b;
-    }
+/// A thumbnail written to disk by `generate_thumbnail`.
+pub struct Thumbnail {
+    pub image: DynamicImage,
+    pub path: PathBuf,
+    pub format: ThumbnailFormat,
+}
 
-    // Force alpha if needed
-    let mut has_alpha = false;
-    for chunk in pixels.chunks(4) {
-        if chunk[3] != 0 {
-            has_alpha = true;
-            break;
+/// Render a thumbnail of `input_path` and save it next to `output_stem`, with
+/// the extension of the format picked for it. The rendered image is returned
+/// so callers can store it elsewhere as well.
+pub fn generate_thumbnail(
+    registry: &ExtractorRegistry,
+    input_path: &Path,
+    output_stem: &Path,
+    max_size: u32,
+) -> Result<Thumbnail, String> {
+    let extension = input_path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    let mime_type = from_path(input_path).first_or_octet_stream().to_string();
+
+    // Try providers in priority order until one renders a preview
+    let mut last_error = None;
+    for extractor in registry.matching(&mime_type, &extension) {
+        match extractor.thumbnail(input_path, max_size) {
+            Ok(Some(img)) => {
+                let image = img.thumbnail(max_size, max_size);
+                let format = ThumbnailFormat::for_image(&image);
+                let path = output_stem.with_extension(format.extension());
+                save_image(&image, &path, format)
+                    .map_err(|e| fl!("error-save-thumbnail", reason = e.to_string()))?;
+                return Ok(Thumbnail {
+                    image,
+                    path,
+                    format,
+                });
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::debug!(
+                    provider = extractor.name(),
+                    path = %input_path.display(),
+                    error = %e,
+                    "Thumbnail provider failed, trying the next one"
+                );
+                last_error = Some(fl!(
+                    "error-extractor-failed",
+                    extractor = extractor.name(),
+                    reason = e
+                ))
+            }
         }
     }
 
-    if !has_alpha {
-        for chunk in pixels.chunks_mut(4) {
-            chunk[3] = 255;
+    Err(last_error.unwrap_or_else(|| fl!("error-no-thumbnail-provider", extension = extension)))
+}
+
+fn save_image(img: &DynamicImage, path: &Path, format: ThumbnailFormat) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let writer = BufWriter::new(file);
+    match format {
+        ThumbnailFormat::Jpeg => img
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(writer, JPEG_QUALITY)),
+        ThumbnailFormat::Webp => img
+            .to_rgba8()
+            .write_with_encoder(WebPEncoder::new_lossless(writer)),
+        ThumbnailFormat::Png => {
+            let mut writer = writer;
+            img.to_rgba8()
+                .write_to(&mut writer, image::ImageFormat::Png)
         }
     }
-
-    let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(width as u32, abs_height as u32, pixels)
-        .ok_or("Failed to create image buffer")?;
-
-    Ok(image::DynamicImage::ImageRgba8(buffer))
+    .map_err(|e| e.to_string())
 }
@@ -0,0 +1,265 @@
+use crate::extractor::{MetadataExtractor, MetadataFields};
+use crate::i18n::fl;
+use image::{DynamicImage, Rgb, RgbImage};
+use lofty::picture::PictureType;
+use lofty::prelude::*;
+use serde_json::Value;
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
+
+/// Samples folded into a single peak while decoding
+const PEAK_BLOCK: usize = 1024;
+
+/// Stretches of a long track the waveform is sampled from, so the cost of a
+/// thumbnail doesn't grow with the length of the recording
+const WAVEFORM_WINDOWS: u64 = 128;
+
+/// Frames decoded from each window
+const WINDOW_FRAMES: u64 = 4096;
+
+const WAVEFORM_BACKGROUND: Rgb<u8> = Rgb([31, 41, 55]);
+const WAVEFORM_COLOR: Rgb<u8> = Rgb([96, 165, 250]);
+
+/// Audio files: tags and embedded cover art through lofty, with a rendered
+/// waveform when there is no artwork.
+pub struct AudioExtractor;
+
+impl MetadataExtractor for AudioExtractor {
+    fn name(&self) -> &'static str {
+        "audio"
+    }
+
+    fn supports(&self, mime_type: &str, extension: &str) -> bool {
+        mime_type.starts_with("audio/")
+            || matches!(
+                extension,
+                "mp3" | "flac" | "m4a" | "ogg" | "oga" | "opus" | "wav" | "aac"
+            )
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let tagged_file = lofty::read_from_path(path).map_err(|e| e.to_string())?;
+        let properties = tagged_file.properties();
+
+        let mut fields = MetadataFields::new();
+        fields.insert(
+            "duration".to_string(),
+            Value::from(properties.duration().as_secs_f64()),
+        );
+        if let Some(sample_rate) = properties.sample_rate() {
+            fields.insert("sample_rate".to_string(), Value::from(sample_rate));
+        }
+        if let Some(channels) = properties.channels() {
+            fields.insert("channels".to_string(), Value::from(channels));
+        }
+        if let Some(tag) = tagged_file.primary_tag().or(tagged_file.first_tag()) {
+            if let Some(title) = tag.title() {
+                fields.insert("title".to_string(), Value::from(title.to_string()));
+            }
+            if let Some(artist) = tag.artist() {
+                fields.insert("artist".to_string(), Value::from(artist.to_string()));
+            }
+            if let Some(album) = tag.album() {
+                fields.insert("album".to_string(), Value::from(album.to_string()));
+            }
+        }
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        if let Some(cover) = cover_art(path) {
+            return Ok(Some(cover.thumbnail(max_size, max_size)));
+        }
+        render_waveform(path, max_size)
+    }
+}
+
+/// The front cover if tagged as such, otherwise the first decodable picture.
+fn cover_art(path: &Path) -> Option<DynamicImage> {
+    let tagged_file = lofty::read_from_path(path).ok()?;
+    let pictures: Vec<_> = tagged_file
+        .tags()
+        .iter()
+        .flat_map(|tag| tag.pictures())
+        .collect();
+
+    pictures
+        .iter()
+        .filter(|p| p.pic_type() == PictureType::CoverFront)
+        .chain(pictures.iter())
+        .find_map(|p| image::load_from_memory(p.data()).ok())
+}
+
+/// `None` when the track length is unknown, leaving the generic audio icon.
+fn render_waveform(path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+    let Some(peaks) = decode_peaks(path)? else {
+        return Ok(None);
+    };
+    if peaks.is_empty() {
+        return Err(fl!("error-no-audio-samples"));
+    }
+
+    let width = max_size;
+    let height = max_size * 3 / 4;
+    let mut img = RgbImage::from_pixel(width, height, WAVEFORM_BACKGROUND);
+
+    // Two pixel wide bars with a one pixel gap, scaled to the loudest peak
+    let columns = (width / 3).max(1) as usize;
+    let loudest = peaks.iter().cloned().fold(f32::EPSILON, f32::max);
+    let center = height / 2;
+    for column in 0..columns {
+        let start = column * peaks.len() / columns;
+        let end = ((column + 1) * peaks.len() / columns).max(start + 1);
+        let peak = peaks[start..end.min(peaks.len())]
+            .iter()
+            .cloned()
+            .fold(0.0, f32::max);
+        let half = ((peak / loudest) * (height as f32 * 0.45)).max(1.0) as u32;
+
+        let x0 = column as u32 * 3;
+        for x in x0..(x0 + 2).min(width) {
+            for y in center.saturating_sub(half)..(center + half).min(height) {
+                img.put_pixel(x, y, WAVEFORM_COLOR);
+            }
+        }
+    }
+
+    Ok(Some(DynamicImage::ImageRgb8(img)))
+}
+
+/// Decode the default track and return the peak amplitude of every block of
+/// `PEAK_BLOCK` samples, all channels mixed. Tracks longer than
+/// `WAVEFORM_WINDOWS` windows are sampled at evenly spaced seek points
+/// instead of decoded in full. `None` when the length of the track is
+/// unknown or it can't be seeked.
+fn decode_peaks(path: &Path) -> Result<Option<Vec<f32>>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| fl!("error-unsupported-audio-format", reason = e.to_string()))?;
+    let mut format = probed.format;
+
+    let track = format
+        .default_track()
+        .ok_or_else(|| fl!("error-no-audio-track"))?;
+    let track_id = track.id;
+    let (Some(n_frames), Some(sample_rate)) =
+        (track.codec_params.n_frames, track.codec_params.sample_rate)
+    else {
+        return Ok(None);
+    };
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| fl!("error-unsupported-audio-codec", reason = e.to_string()))?;
+
+    let mut peaks = Vec::new();
+    let mut samples: Option<SampleBuffer<f32>> = None;
+
+    if n_frames <= WAVEFORM_WINDOWS * WINDOW_FRAMES {
+        decode_window(
+            format.as_mut(),
+            decoder.as_mut(),
+            track_id,
+            n_frames,
+            &mut samples,
+            &mut peaks,
+        )?;
+        return Ok(Some(peaks));
+    }
+
+    for window in 0..WAVEFORM_WINDOWS {
+        let frame = n_frames / WAVEFORM_WINDOWS * window;
+        let seek = SeekTo::Time {
+            time: Time::from(frame as f64 / sample_rate as f64),
+            track_id: Some(track_id),
+        };
+        if format.seek(SeekMode::Coarse, seek).is_err() {
+            return Ok(None);
+        }
+        decoder.reset();
+        decode_window(
+            format.as_mut(),
+            decoder.as_mut(),
+            track_id,
+            WINDOW_FRAMES,
+            &mut samples,
+            &mut peaks,
+        )?;
+    }
+    Ok(Some(peaks))
+}
+
+/// Decode up to `max_frames` frames from the current position, appending
+/// their peaks. Stops early at the end of the stream.
+fn decode_window(
+    format: &mut dyn FormatReader,
+    decoder: &mut dyn Decoder,
+    track_id: u32,
+    max_frames: u64,
+    samples: &mut Option<SampleBuffer<f32>>,
+    peaks: &mut Vec<f32>,
+) -> Result<(), String> {
+    let mut frames = 0;
+    let mut block_peak = 0.0f32;
+    let mut block_len = 0;
+
+    while frames < max_frames {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(_) => break, // End of stream
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Corrupt packets are skipped rather than failing the whole file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.to_string()),
+        };
+        frames += decoded.frames() as u64;
+
+        let buffer = samples
+            .get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, *decoded.spec()));
+        if buffer.capacity() < decoded.capacity() * decoded.spec().channels.count() {
+            *buffer = SampleBuffer::new(decoded.capacity() as u64, *decoded.spec());
+        }
+        buffer.copy_interleaved_ref(decoded);
+
+        for sample in buffer.samples() {
+            block_peak = block_peak.max(sample.abs());
+            block_len += 1;
+            if block_len == PEAK_BLOCK {
+                peaks.push(block_peak);
+                block_peak = 0.0;
+                block_len = 0;
+            }
+        }
+    }
+
+    if block_len > 0 {
+        peaks.push(block_peak);
+    }
+    Ok(())
+}
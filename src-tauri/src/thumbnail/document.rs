@@ -0,0 +1,534 @@
+use crate::extractor::{MetadataExtractor, MetadataFields};
+use crate::i18n::fl;
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use image::{DynamicImage, Rgb, RgbImage};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+use zip::ZipArchive;
+
+// --- EPUB ---
+
+/// EPUB books: title and author from the package document, cover image as
+/// the thumbnail.
+pub struct EpubExtractor;
+
+impl MetadataExtractor for EpubExtractor {
+    fn name(&self) -> &'static str {
+        "epub"
+    }
+
+    fn supports(&self, mime_type: &str, extension: &str) -> bool {
+        mime_type == "application/epub+zip" || extension == "epub"
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let mut archive = open_zip(path)?;
+        let (_, opf) = read_package_document(&mut archive)?;
+
+        let mut fields = MetadataFields::new();
+        for (element, key) in [("title", "title"), ("creator", "author")] {
+            if let Some(text) = first_element_text(&opf, element) {
+                fields.insert(key.to_string(), Value::from(text));
+            }
+        }
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        let mut archive = open_zip(path)?;
+        let (opf_path, opf) = read_package_document(&mut archive)?;
+        let href = cover_href(&opf).ok_or_else(|| fl!("error-no-cover"))?;
+        let cover_path = resolve_relative(&opf_path, &href);
+        let data = read_entry(&mut archive, &cover_path)?;
+        let img = image::load_from_memory(&data)
+            .map_err(|e| fl!("error-decode-image", reason = e.to_string()))?;
+        Ok(Some(img.thumbnail(max_size, max_size)))
+    }
+}
+
+fn read_package_document<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<(String, String), String> {
+    let container = read_entry_string(archive, "META-INF/container.xml")?;
+    let opf_path = find_elements(&container, "rootfile")
+        .into_iter()
+        .find_map(|attrs| attr_value(&attrs, "full-path"))
+        .ok_or_else(|| fl!("error-no-package-document"))?;
+    let opf = read_entry_string(archive, &opf_path)?;
+    Ok((opf_path, opf))
+}
+
+/// Cover image href from the manifest, following EPUB 3 (`properties`),
+/// EPUB 2 (`<meta name="cover">`) and finally a filename heuristic.
+fn cover_href(opf: &str) -> Option<String> {
+    let items = find_elements(opf, "item");
+    let is_image = |attrs: &[(String, String)]| {
+        attr_value(attrs, "media-type").is_some_and(|t| t.starts_with("image/"))
+    };
+
+    if let Some(item) = items.iter().find(|attrs| {
+        attr_value(attrs, "properties")
+            .is_some_and(|p| p.split_whitespace().any(|p| p == "cover-image"))
+    }) {
+        return attr_value(item, "href");
+    }
+
+    let cover_id = find_elements(opf, "meta")
+        .into_iter()
+        .find(|attrs| attr_value(attrs, "name").as_deref() == Some("cover"))
+        .and_then(|attrs| attr_value(&attrs, "content"));
+    if let Some(cover_id) = cover_id {
+        if let Some(item) = items
+            .iter()
+            .find(|attrs| attr_value(attrs, "id").as_deref() == Some(cover_id.as_str()))
+        {
+            return attr_value(item, "href");
+        }
+    }
+
+    items
+        .iter()
+        .filter(|attrs| is_image(attrs))
+        .find(|attrs| {
+            attr_value(attrs, "id")
+                .into_iter()
+                .chain(attr_value(attrs, "href"))
+                .any(|v| v.to_lowercase().contains("cover"))
+        })
+        .and_then(|attrs| attr_value(attrs, "href"))
+}
+
+// --- Office documents ---
+
+/// OOXML (docx/xlsx/pptx) and ODF (odt/ods/odp) packages: core properties
+/// and the thumbnail the authoring application embedded when saving.
+pub struct OfficeExtractor;
+
+impl MetadataExtractor for OfficeExtractor {
+    fn name(&self) -> &'static str {
+        "office"
+    }
+
+    fn supports(&self, _mime_type: &str, extension: &str) -> bool {
+        matches!(
+            extension,
+            "docx" | "docm" | "xlsx" | "xlsm" | "pptx" | "pptm" | "odt" | "ods" | "odp" | "odg"
+        )
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let mut archive = open_zip(path)?;
+        // OOXML keeps properties in docProps/core.xml, ODF in meta.xml
+        let properties = read_entry_string(&mut archive, "docProps/core.xml")
+            .or_else(|_| read_entry_string(&mut archive, "meta.xml"))?;
+
+        let mut fields = MetadataFields::new();
+        for (element, key) in [("title", "title"), ("creator", "author")] {
+            if let Some(text) = first_element_text(&properties, element) {
+                fields.insert(key.to_string(), Value::from(text));
+            }
+        }
+        if let Some(pages) = read_entry_string(&mut archive, "docProps/app.xml")
+            .ok()
+            .and_then(|app| first_element_text(&app, "Pages"))
+            .and_then(|p| p.parse::<u64>().ok())
+        {
+            fields.insert("page_count".to_string(), Value::from(pages));
+        }
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        let mut archive = open_zip(path)?;
+
+        // OOXML declares its thumbnail in the package relationships
+        let mut candidates: Vec<String> = read_entry_string(&mut archive, "_rels/.rels")
+            .map(|rels| {
+                find_elements(&rels, "Relationship")
+                    .into_iter()
+                    .filter(|attrs| {
+                        attr_value(attrs, "Type").is_some_and(|t| t.ends_with("/thumbnail"))
+                    })
+                    .filter_map(|attrs| attr_value(&attrs, "Target"))
+                    .map(|target| target.trim_start_matches('/').to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        candidates.extend(
+            [
+                "docProps/thumbnail.jpeg",
+                "docProps/thumbnail.png",
+                "Thumbnails/thumbnail.png",
+            ]
+            .map(String::from),
+        );
+
+        for candidate in candidates {
+            // WMF/EMF thumbnails are skipped; the image crate can't decode them
+            if let Ok(data) = read_entry(&mut archive, &candidate) {
+                if let Ok(img) = image::load_from_memory(&data) {
+                    return Ok(Some(img.thumbnail(max_size, max_size)));
+                }
+            }
+        }
+        Err(fl!("error-no-embedded-thumbnail"))
+    }
+}
+
+// --- Plain text and source code ---
+
+/// Only the head of the file is read for previews
+const TEXT_PREVIEW_BYTES: u64 = 64 * 1024;
+const TEXT_PREVIEW_LINES: usize = 24;
+const TEXT_THEME: &str = "base16-ocean.dark";
+
+/// Plain text, Markdown and source files, rendered as a syntax highlighted
+/// excerpt of their first lines.
+pub struct TextExtractor;
+
+impl MetadataExtractor for TextExtractor {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
+    fn supports(&self, mime_type: &str, extension: &str) -> bool {
+        mime_type.starts_with("text/")
+            || matches!(
+                extension,
+                "md" | "markdown"
+                    | "txt"
+                    | "log"
+                    | "ini"
+                    | "cfg"
+                    | "toml"
+                    | "yaml"
+                    | "yml"
+                    | "json"
+                    | "rs"
+                    | "py"
+                    | "js"
+                    | "ts"
+                    | "tsx"
+                    | "jsx"
+                    | "vue"
+                    | "go"
+                    | "java"
+                    | "kt"
+                    | "c"
+                    | "h"
+                    | "cpp"
+                    | "hpp"
+                    | "cs"
+                    | "rb"
+                    | "php"
+                    | "sh"
+                    | "ps1"
+                    | "sql"
+                    | "lua"
+                    | "swift"
+            )
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let text = read_text_head(path)?;
+        let mut fields = MetadataFields::new();
+        let syntax = find_syntax(path, &text);
+        if syntax.name != syntax_set().find_syntax_plain_text().name {
+            fields.insert("language".to_string(), Value::from(syntax.name.clone()));
+        }
+        fields.insert(
+            "line_count_preview".to_string(),
+            Value::from(text.lines().count()),
+        );
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        let text = read_text_head(path)?;
+        Ok(Some(render_text(path, &text, max_size)))
+    }
+}
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults();
+        themes.themes.remove(TEXT_THEME).unwrap_or_default()
+    })
+}
+
+/// The system's default monospace font, if one can be found.
+fn monospace_font() -> Option<&'static FontVec> {
+    static FONT: OnceLock<Option<FontVec>> = OnceLock::new();
+    FONT.get_or_init(|| {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        let id = db.query(&fontdb::Query {
+            families: &[fontdb::Family::Monospace],
+            ..Default::default()
+        })?;
+        db.with_face_data(id, |data, index| {
+            FontVec::try_from_vec_and_index(data.to_vec(), index).ok()
+        })
+        .flatten()
+    })
+    .as_ref()
+}
+
+fn find_syntax(path: &Path, text: &str) -> &'static SyntaxReference {
+    let syntaxes = syntax_set();
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(|extension| syntaxes.find_syntax_by_extension(extension))
+        .or_else(|| syntaxes.find_syntax_by_first_line(text.lines().next().unwrap_or("")))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+fn read_text_head(path: &Path) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    File::open(path)
+        .and_then(|f| f.take(TEXT_PREVIEW_BYTES).read_to_end(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    if bytes.contains(&0) {
+        return Err(fl!("error-binary-content"));
+    }
+    Ok(String::from_utf8_lossy(&bytes).replace('\t', "    "))
+}
+
+fn render_text(path: &Path, text: &str, max_size: u32) -> DynamicImage {
+    let syntaxes = syntax_set();
+    let theme = theme();
+    let syntax = find_syntax(path, text);
+
+    let background = theme
+        .settings
+        .background
+        .map(|c| Rgb([c.r, c.g, c.b]))
+        .unwrap_or(Rgb([43, 48, 59]));
+    let mut img = RgbImage::from_pixel(max_size, max_size, background);
+
+    let margin = (max_size / 24).max(2) as f32;
+    let line_height = (max_size as f32 - margin * 2.0) / TEXT_PREVIEW_LINES as f32;
+    let scale = PxScale::from(line_height * 0.9);
+    let font = monospace_font();
+    // Without a font each character becomes a block, like an editor minimap
+    let advance = match font {
+        Some(font) => font.as_scaled(scale).h_advance(font.glyph_id('M')),
+        None => line_height * 0.5,
+    };
+
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    for (row, line) in LinesWithEndings::from(text)
+        .take(TEXT_PREVIEW_LINES)
+        .enumerate()
+    {
+        let Ok(ranges) = highlighter.highlight_line(line, syntaxes) else {
+            break;
+        };
+        let top = margin + row as f32 * line_height;
+        let mut x = margin;
+        for (style, piece) in ranges {
+            let color = Rgb([style.foreground.r, style.foreground.g, style.foreground.b]);
+            for ch in piece.chars() {
+                if x + advance > max_size as f32 - margin {
+                    break;
+                }
+                if !ch.is_whitespace() {
+                    match font {
+                        Some(font) => draw_glyph(&mut img, font, scale, ch, x, top, color),
+                        None => fill_rect(
+                            &mut img,
+                            x,
+                            top + line_height * 0.25,
+                            advance * 0.8,
+                            line_height * 0.5,
+                            color,
+                        ),
+                    }
+                }
+                x += advance;
+            }
+        }
+    }
+
+    DynamicImage::ImageRgb8(img)
+}
+
+fn draw_glyph(
+    img: &mut RgbImage,
+    font: &FontVec,
+    scale: PxScale,
+    ch: char,
+    x: f32,
+    top: f32,
+    color: Rgb<u8>,
+) {
+    let scaled = font.as_scaled(scale);
+    let glyph = scaled
+        .glyph_id(ch)
+        .with_scale_and_position(scale, ab_glyph::point(x, top + scaled.ascent()));
+    let Some(outlined) = font.outline_glyph(glyph) else {
+        return;
+    };
+    let bounds = outlined.px_bounds();
+    outlined.draw(|gx, gy, coverage| {
+        let px = bounds.min.x as i32 + gx as i32;
+        let py = bounds.min.y as i32 + gy as i32;
+        if px < 0 || py < 0 || px >= img.width() as i32 || py >= img.height() as i32 {
+            return;
+        }
+        let pixel = img.get_pixel_mut(px as u32, py as u32);
+        for channel in 0..3 {
+            let blended =
+                pixel.0[channel] as f32 * (1.0 - coverage) + color.0[channel] as f32 * coverage;
+            pixel.0[channel] = blended.round() as u8;
+        }
+    });
+}
+
+fn fill_rect(img: &mut RgbImage, x: f32, y: f32, width: f32, height: f32, color: Rgb<u8>) {
+    let x_end = ((x + width) as u32).min(img.width());
+    let y_end = ((y + height) as u32).min(img.height());
+    for py in y as u32..y_end {
+        for px in x as u32..x_end {
+            img.put_pixel(px, py, color);
+        }
+    }
+}
+
+// --- Zip/XML helpers ---
+
+fn open_zip(path: &Path) -> Result<ZipArchive<File>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    ZipArchive::new(file).map_err(|e| fl!("error-invalid-package", reason = e.to_string()))
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = archive.by_name(name).map_err(|e| {
+        fl!(
+            "error-read-package-entry",
+            name = name,
+            reason = e.to_string()
+        )
+    })?;
+    let mut data = Vec::new();
+    entry.read_to_end(&mut data).map_err(|e| e.to_string())?;
+    Ok(data)
+}
+
+fn read_entry_string<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<String, String> {
+    read_entry(archive, name).map(|data| String::from_utf8_lossy(&data).into_owned())
+}
+
+/// Resolve `href` against the directory of `base` inside a zip package.
+fn resolve_relative(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or(href);
+    let mut parts: Vec<&str> = base.split('/').collect();
+    parts.pop(); // Drop the file name of the base
+    for segment in href.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(segment),
+        }
+    }
+    let joined = parts.join("/");
+    percent_decode(&joined)
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16);
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+type Attributes = Vec<(String, String)>;
+
+fn attr_value(attrs: &[(String, String)], name: &str) -> Option<String> {
+    attrs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone())
+}
+
+fn collect_attributes(element: &BytesStart) -> Attributes {
+    element
+        .attributes()
+        .flatten()
+        .filter_map(|attr| {
+            let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned();
+            let value = attr.unescape_value().ok()?.into_owned();
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Attributes of every element with the given local name (namespace ignored).
+fn find_elements(xml: &str, local_name: &str) -> Vec<Attributes> {
+    let mut reader = Reader::from_str(xml);
+    let mut found = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e))
+                if e.local_name().as_ref() == local_name.as_bytes() =>
+            {
+                found.push(collect_attributes(&e));
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Text content of the first element with the given local name.
+fn first_element_text(xml: &str, local_name: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut inside = false;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) if e.local_name().as_ref() == local_name.as_bytes() => {
+                inside = true;
+            }
+            Ok(Event::Text(t)) if inside => {
+                let text = t.unescape().ok()?.trim().to_string();
+                return (!text.is_empty()).then_some(text);
+            }
+            Ok(Event::End(_)) if inside => return None,
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Shared thumbnail cache from the freedesktop.org Thumbnail Managing Standard,
+//! as maintained by GNOME, KDE and most Linux file managers.
+
+use crate::i18n::fl;
+use image::DynamicImage;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Size buckets defined by the spec, smallest first.
+const BUCKETS: [(&str, u32); 4] = [
+    ("normal", 128),
+    ("large", 256),
+    ("x-large", 512),
+    ("xx-large", 1024),
+];
+
+fn cache_root() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("thumbnails"))
+}
+
+/// `file://` URI of an absolute path, escaped the way GLib does it so our
+/// hashes match the ones other applications compute.
+pub fn file_uri(path: &Path) -> Option<String> {
+    let absolute = path.canonicalize().ok()?;
+    let mut uri = String::from("file://");
+    for byte in absolute.to_string_lossy().bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' => uri.push(byte as char),
+            b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' | b':'
+            | b'@' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    Some(uri)
+}
+
+fn thumbnail_name(uri: &str) -> String {
+    format!("{:x}.png", md5::compute(uri.as_bytes()))
+}
+
+/// Find an up-to-date thumbnail of `input_path` at least roughly `max_size`
+/// pixels large. Buckets down to 3/4 of the requested size are accepted so a
+/// 256px "large" thumbnail can serve a 320px request.
+pub fn lookup(input_path: &Path, max_size: u32, modified_time: i64) -> Option<PathBuf> {
+    let root = cache_root()?;
+    let uri = file_uri(input_path)?;
+    let name = thumbnail_name(&uri);
+
+    BUCKETS
+        .iter()
+        .filter(|(_, size)| *size * 4 >= max_size * 3)
+        .map(|(bucket, _)| root.join(bucket).join(&name))
+        .find(|candidate| is_valid(candidate, &uri, modified_time))
+}
+
+fn is_valid(thumb_path: &Path, uri: &str, modified_time: i64) -> bool {
+    let Ok(file) = File::open(thumb_path) else {
+        return false;
+    };
+    let Ok(reader) = png::Decoder::new(file).read_info() else {
+        return false;
+    };
+    let info = reader.info();
+
+    let text = |key: &str| {
+        info.uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == key)
+            .map(|chunk| chunk.text.clone())
+            .or_else(|| {
+                info.utf8_text
+                    .iter()
+                    .find(|chunk| chunk.keyword == key)
+                    .and_then(|chunk| chunk.get_text().ok())
+            })
+    };
+
+    text("Thumb::URI").as_deref() == Some(uri)
+        && text("Thumb::MTime").and_then(|m| m.parse::<i64>().ok()) == Some(modified_time)
+}
+
+/// Write `img` into the shared cache, in the largest bucket not exceeding
+/// `max_size`, so other applications never get an upscaled thumbnail. Sizes
+/// below the smallest bucket are not shared. Errors are not fatal for the
+/// caller.
+pub fn store(
+    img: &DynamicImage,
+    input_path: &Path,
+    max_size: u32,
+    modified_time: i64,
+) -> Result<(), String> {
+    let root = cache_root().ok_or_else(|| fl!("error-no-cache-dir"))?;
+    let uri = file_uri(input_path).ok_or_else(|| {
+        fl!(
+            "error-path-not-found",
+            path = input_path.display().to_string()
+        )
+    })?;
+    let Some((bucket, bucket_size)) = BUCKETS.iter().rev().find(|(_, size)| *size <= max_size)
+    else {
+        return Ok(());
+    };
+
+    let dir = root.join(bucket);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let rgba = img.thumbnail(*bucket_size, *bucket_size).to_rgba8();
+    let file_size = fs::metadata(input_path).map(|m| m.len()).unwrap_or(0);
+
+    // Write to a temporary file first so readers never see a partial PNG
+    let name = thumbnail_name(&uri);
+    let final_path = dir.join(&name);
+    let temp_path = dir.join(format!("{}.{}.tmp", name, std::process::id()));
+    {
+        let file = File::create(&temp_path).map_err(|e| e.to_string())?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), rgba.width(), rgba.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        for (key, value) in [
+            ("Thumb::URI", uri.clone()),
+            ("Thumb::MTime", modified_time.to_string()),
+            ("Thumb::Size", file_size.to_string()),
+            ("Software", "InchBox".to_string()),
+        ] {
+            encoder
+                .add_text_chunk(key.to_string(), value)
+                .map_err(|e| e.to_string())?;
+        }
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer
+            .write_image_data(rgba.as_raw())
+            .map_err(|e| e.to_string())?;
+    }
+
+    // The spec requires thumbnails to be private to the user
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o600));
+    }
+
+    fs::rename(&temp_path, &final_path).map_err(|e| e.to_string())
+}
@@ -0,0 +1,192 @@
+use crate::extractor::{MetadataExtractor, MetadataFields};
+use crate::i18n::fl;
+use image::DynamicImage;
+use pdfium_render::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Overrides the configured pdfium location, e.g. for packaged builds
+const PDFIUM_ENV: &str = "INCHBOX_PDFIUM";
+
+/// Resolution of full-size page previews
+const PREVIEW_DPI: f32 = 150.0;
+
+/// Longest edge of a full-size page preview, so posters don't render at
+/// hundreds of megapixels
+const MAX_PREVIEW_EDGE: u16 = 8192;
+
+static CONFIGURED_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+static PDFIUM: OnceLock<Result<Binding, String>> = OnceLock::new();
+
+struct Binding {
+    pdfium: Pdfium,
+    library: String,
+}
+
+/// Whether PDF previews are available, for the settings screen.
+#[derive(Debug, Serialize, Clone)]
+pub struct PdfiumStatus {
+    pub available: bool,
+    /// Library that was loaded, or `system` when found on the search path
+    pub library: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Set where to look for pdfium before it is first used. The library is
+/// loaded once per process, so later changes only apply after a restart.
+pub fn configure(library_path: Option<PathBuf>) {
+    let _ = CONFIGURED_PATH.set(library_path);
+}
+
+pub fn status() -> PdfiumStatus {
+    match binding() {
+        Ok(binding) => PdfiumStatus {
+            available: true,
+            library: Some(binding.library.clone()),
+            error: None,
+        },
+        Err(e) => PdfiumStatus {
+            available: false,
+            library: None,
+            error: Some(e),
+        },
+    }
+}
+
+fn binding() -> Result<&'static Binding, String> {
+    PDFIUM.get_or_init(bind).as_ref().map_err(|e| e.clone())
+}
+
+fn pdfium() -> Result<&'static Pdfium, String> {
+    binding().map(|b| &b.pdfium)
+}
+
+/// Try the environment override, the configured path and the app directory
+/// in that order, then the system library.
+fn bind() -> Result<Binding, String> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(path) = std::env::var_os(PDFIUM_ENV) {
+        candidates.push(PathBuf::from(path));
+    }
+    if let Some(Some(path)) = CONFIGURED_PATH.get() {
+        candidates.push(path.clone());
+    }
+    candidates.push(PathBuf::from("./"));
+
+    let mut errors = Vec::new();
+    for candidate in candidates {
+        // A directory is searched for the platform's library name
+        let library = if candidate.is_dir() {
+            Pdfium::pdfium_platform_library_name_at_path(&candidate)
+        } else {
+            candidate
+        };
+        match Pdfium::bind_to_library(&library) {
+            Ok(bindings) => {
+                tracing::info!(library = %library.display(), "Loaded pdfium");
+                return Ok(Binding {
+                    pdfium: Pdfium::new(bindings),
+                    library: library.to_string_lossy().to_string(),
+                });
+            }
+            Err(e) => errors.push(format!("{}: {}", library.display(), e)),
+        }
+    }
+
+    match Pdfium::bind_to_system_library() {
+        Ok(bindings) => Ok(Binding {
+            pdfium: Pdfium::new(bindings),
+            library: "system".to_string(),
+        }),
+        Err(e) => {
+            errors.push(format!("system: {}", e));
+            let message = format!("pdfium library not found ({})", errors.join("; "));
+            tracing::warn!("{}", message);
+            Err(message)
+        }
+    }
+}
+
+/// PDF documents, rendered through pdfium.
+pub struct PdfExtractor;
+
+impl MetadataExtractor for PdfExtractor {
+    fn name(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn supports(&self, mime_type: &str, extension: &str) -> bool {
+        mime_type == "application/pdf" || extension == "pdf"
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let mut fields = MetadataFields::new();
+        fields.insert("page_count".to_string(), Value::from(page_count(path)?));
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        render_page(path, 0, Some(max_size)).map(Some)
+    }
+}
+
+pub fn page_count(path: &Path) -> Result<u16, String> {
+    Ok(load_document(path)?.pages().len())
+}
+
+/// Render the zero-based `page_index`, scaled to fit `max_size`, or at
+/// `PREVIEW_DPI` when no size is given.
+pub fn render_page(
+    path: &Path,
+    page_index: u16,
+    max_size: Option<u32>,
+) -> Result<DynamicImage, String> {
+    let document = load_document(path)?;
+    let page_count = document.pages().len();
+    if page_index >= page_count {
+        return Err(fl!(
+            "error-page-out-of-range",
+            page = page_index as u32 + 1,
+            page_count = page_count
+        ));
+    }
+
+    let page = document.pages().get(page_index).map_err(|e| {
+        fl!(
+            "error-load-pdf-page",
+            page = page_index as u32 + 1,
+            reason = e.to_string()
+        )
+    })?;
+
+    let config = match max_size {
+        Some(max_size) => {
+            // A plain cast would wrap sizes past u16 to tiny ones
+            let edge = max_size.clamp(1, MAX_PREVIEW_EDGE.into()) as u16;
+            PdfRenderConfig::new()
+                .set_target_width(edge.into())
+                .set_maximum_height(edge.into())
+        }
+        None => PdfRenderConfig::new()
+            .scale_page_by_factor(PREVIEW_DPI / 72.0)
+            .set_maximum_width(MAX_PREVIEW_EDGE.into())
+            .set_maximum_height(MAX_PREVIEW_EDGE.into()),
+    };
+    let bitmap = page.render_with_config(&config).map_err(|e| {
+        fl!(
+            "error-render-pdf-page",
+            page = page_index as u32 + 1,
+            reason = e.to_string()
+        )
+    })?;
+
+    Ok(bitmap.as_image())
+}
+
+fn load_document(path: &Path) -> Result<PdfDocument<'static>, String> {
+    pdfium()?
+        .load_pdf_from_file(path, None)
+        .map_err(|e| fl!("error-load-pdf", reason = e.to_string()))
+}
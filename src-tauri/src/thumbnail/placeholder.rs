@@ -0,0 +1,132 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Blurhash components along the longer edge of the image
+const BLURHASH_COMPONENTS: u32 = 4;
+
+/// Images are reduced to fit this size before being analysed
+const SAMPLE_SIZE: u32 = 64;
+
+const MAX_DOMINANT_COLORS: usize = 5;
+
+/// Colors covering less of the image than this are not reported
+const MIN_COLOR_SHARE: f32 = 0.03;
+
+/// Dominant colors closer than this to one already picked are skipped
+const MIN_COLOR_DISTANCE: f32 = 60.0;
+
+/// Default distance within which a dominant color counts as a match
+pub const DEFAULT_COLOR_TOLERANCE: f32 = 90.0;
+
+/// What the grid shows while the real thumbnail is loading.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Placeholder {
+    pub blurhash: String,
+    /// Dominant colors as `#rrggbb`, most prominent first
+    pub colors: Vec<String>,
+}
+
+impl Placeholder {
+    /// Whether any dominant color lies within `tolerance` of `target`.
+    pub fn has_color(&self, target: [u8; 3], tolerance: f32) -> bool {
+        self.colors
+            .iter()
+            .filter_map(|c| parse_hex_color(c))
+            .any(|c| color_distance(c, target) <= tolerance)
+    }
+}
+
+pub fn compute(img: &DynamicImage) -> Result<Placeholder, String> {
+    let sample = img
+        .resize(SAMPLE_SIZE, SAMPLE_SIZE, FilterType::Triangle)
+        .to_rgba8();
+    let (width, height) = sample.dimensions();
+    if width == 0 || height == 0 {
+        return Err("Empty image".to_string());
+    }
+
+    // Keep the components roughly proportional to the aspect ratio
+    let (components_x, components_y) = if width >= height {
+        (
+            BLURHASH_COMPONENTS,
+            (BLURHASH_COMPONENTS * height / width).clamp(1, BLURHASH_COMPONENTS),
+        )
+    } else {
+        (
+            (BLURHASH_COMPONENTS * width / height).clamp(1, BLURHASH_COMPONENTS),
+            BLURHASH_COMPONENTS,
+        )
+    };
+    let blurhash = blurhash::encode(components_x, components_y, width, height, sample.as_raw())
+        .map_err(|e| format!("Failed to encode blurhash: {}", e))?;
+
+    Ok(Placeholder {
+        blurhash,
+        colors: dominant_colors(&sample)
+            .into_iter()
+            .map(|[r, g, b]| format!("#{:02x}{:02x}{:02x}", r, g, b))
+            .collect(),
+    })
+}
+
+/// Bucket pixels into a 16 levels per channel palette and average each of
+/// the most populated buckets. Transparent pixels are ignored.
+fn dominant_colors(sample: &image::RgbaImage) -> Vec<[u8; 3]> {
+    let mut buckets: HashMap<[u8; 3], ([u64; 3], u64)> = HashMap::new();
+    let mut opaque = 0u64;
+    for pixel in sample.pixels() {
+        let [r, g, b, a] = pixel.0;
+        if a < 128 {
+            continue;
+        }
+        opaque += 1;
+        let (sum, count) = buckets.entry([r >> 4, g >> 4, b >> 4]).or_default();
+        sum[0] += r as u64;
+        sum[1] += g as u64;
+        sum[2] += b as u64;
+        *count += 1;
+    }
+
+    let mut ranked: Vec<([u64; 3], u64)> = buckets.into_values().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut colors: Vec<[u8; 3]> = Vec::new();
+    for (sum, count) in ranked {
+        if colors.len() == MAX_DOMINANT_COLORS || (count as f32) < opaque as f32 * MIN_COLOR_SHARE {
+            break;
+        }
+        let color = sum.map(|channel| (channel / count) as u8);
+        if colors
+            .iter()
+            .all(|picked| color_distance(*picked, color) >= MIN_COLOR_DISTANCE)
+        {
+            colors.push(color);
+        }
+    }
+    colors
+}
+
+/// Parse `#rrggbb` (the `#` is optional).
+pub fn parse_hex_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Approximate perceptual distance between two sRGB colors ("redmean"),
+/// from 0 up to roughly 765.
+pub fn color_distance(a: [u8; 3], b: [u8; 3]) -> f32 {
+    let mean_red = (a[0] as f32 + b[0] as f32) / 2.0;
+    let dr = a[0] as f32 - b[0] as f32;
+    let dg = a[1] as f32 - b[1] as f32;
+    let db = a[2] as f32 - b[2] as f32;
+    ((2.0 + mean_red / 256.0) * dr * dr
+        + 4.0 * dg * dg
+        + (2.0 + (255.0 - mean_red) / 256.0) * db * db)
+        .sqrt()
+}
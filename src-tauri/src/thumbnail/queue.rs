@@ -0,0 +1,214 @@
+use serde::Deserialize;
+use std::collections::{BinaryHeap, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use tokio::sync::oneshot;
+
+/// Upper bound on thumbnails rendered at the same time
+pub const MAX_CONCURRENT_THUMBNAILS: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Near the viewport, worth preparing ahead of time
+    Prefetch,
+    /// Currently on screen
+    #[default]
+    Visible,
+}
+
+/// Why a queued request produced no thumbnail
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobError {
+    /// Withdrawn by the caller before the job ran
+    Cancelled,
+    /// The job ran and failed with this reason
+    Failed(String),
+}
+
+pub type JobResult = Result<String, JobError>;
+type Job = Box<dyn FnOnce() -> Result<String, String> + Send>;
+
+struct PendingJob {
+    priority: Priority,
+    seq: u64,
+    job: Job,
+}
+
+// Ordered by priority, then newest first: the most recent requests are the
+// ones the user just scrolled to.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct HeapEntry {
+    priority: Priority,
+    seq: u64,
+    key: String,
+}
+
+struct Waiter {
+    /// Chosen by the caller to withdraw this request alone
+    request_id: Option<String>,
+    tx: oneshot::Sender<JobResult>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    heap: BinaryHeap<HeapEntry>,
+    pending: HashMap<String, PendingJob>,
+    // Everyone waiting on a key, whether it is still queued or already running
+    waiters: HashMap<String, Vec<Waiter>>,
+    next_seq: u64,
+}
+
+impl QueueState {
+    fn next_job(&mut self) -> Option<(String, Job)> {
+        while let Some(entry) = self.heap.pop() {
+            // Entries left behind by a priority bump or a cancellation are stale
+            let is_current = self
+                .pending
+                .get(&entry.key)
+                .is_some_and(|p| p.seq == entry.seq);
+            if is_current {
+                let pending = self.pending.remove(&entry.key)?;
+                return Some((entry.key, pending.job));
+            }
+        }
+        None
+    }
+}
+
+/// Bounded, prioritized work queue for thumbnail generation. Requests for a
+/// key that is already queued or running share a single job.
+#[derive(Clone)]
+pub struct ThumbnailQueue {
+    shared: Arc<(Mutex<QueueState>, Condvar)>,
+}
+
+impl ThumbnailQueue {
+    pub fn new(workers: usize) -> Self {
+        let queue = Self {
+            shared: Arc::new((Mutex::new(QueueState::default()), Condvar::new())),
+        };
+        for i in 0..workers.max(1) {
+            let shared = queue.shared.clone();
+            let _ = thread::Builder::new()
+                .name(format!("thumbnail-{}", i))
+                .spawn(move || worker_loop(shared));
+        }
+        queue
+    }
+
+    /// Queue `job` under `key`, or join the job already queued for it.
+    /// `request_id` lets the caller cancel this request later.
+    pub fn submit<F>(
+        &self,
+        key: &str,
+        request_id: Option<String>,
+        priority: Priority,
+        job: F,
+    ) -> oneshot::Receiver<JobResult>
+    where
+        F: FnOnce() -> Result<String, String> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let waiter = Waiter { request_id, tx };
+        let (lock, cvar) = &*self.shared;
+        let mut guard = lock.lock().unwrap();
+        let state = &mut *guard;
+        state.next_seq += 1;
+        let seq = state.next_seq;
+
+        if let Some(waiters) = state.waiters.get_mut(key) {
+            waiters.push(waiter);
+            // Still queued: move it to the front of its (possibly raised) priority
+            if let Some(pending) = state.pending.get_mut(key) {
+                pending.priority = pending.priority.max(priority);
+                pending.seq = seq;
+                let priority = pending.priority;
+                state.heap.push(HeapEntry {
+                    priority,
+                    seq,
+                    key: key.to_string(),
+                });
+            }
+            return rx;
+        }
+
+        state.waiters.insert(key.to_string(), vec![waiter]);
+        state.pending.insert(
+            key.to_string(),
+            PendingJob {
+                priority,
+                seq,
+                job: Box::new(job),
+            },
+        );
+        state.heap.push(HeapEntry {
+            priority,
+            seq,
+            key: key.to_string(),
+        });
+        cvar.notify_one();
+        rx
+    }
+
+    /// Withdraw the requests submitted with `request_ids`. Each is answered
+    /// with [`JobError::Cancelled`], and a queued job is only dropped once nobody is
+    /// left waiting for it. Requests for jobs already running are left to
+    /// finish. Returns how many jobs were dropped.
+    pub fn cancel(&self, request_ids: &[String]) -> usize {
+        let (lock, _) = &*self.shared;
+        let mut guard = lock.lock().unwrap();
+        let state = &mut *guard;
+        let mut cancelled = 0;
+        for request_id in request_ids {
+            let is_request = |waiter: &Waiter| waiter.request_id.as_ref() == Some(request_id);
+            let Some((key, waiters)) = state
+                .waiters
+                .iter_mut()
+                .find(|(_, waiters)| waiters.iter().any(is_request))
+            else {
+                continue;
+            };
+            if !state.pending.contains_key(key) {
+                continue;
+            }
+            if let Some(position) = waiters.iter().position(is_request) {
+                let _ = waiters.remove(position).tx.send(Err(JobError::Cancelled));
+            }
+            // Requests whose caller went away don't keep the job either
+            waiters.retain(|waiter| !waiter.tx.is_closed());
+            if waiters.is_empty() {
+                let key = key.clone();
+                state.waiters.remove(&key);
+                state.pending.remove(&key);
+                cancelled += 1;
+            }
+        }
+        cancelled
+    }
+}
+
+fn worker_loop(shared: Arc<(Mutex<QueueState>, Condvar)>) {
+    let (lock, cvar) = &*shared;
+    loop {
+        let (key, job) = {
+            let mut state = lock.lock().unwrap();
+            loop {
+                if let Some(next) = state.next_job() {
+                    break next;
+                }
+                state = cvar.wait(state).unwrap();
+            }
+        };
+
+        let result = panic::catch_unwind(AssertUnwindSafe(job))
+            .unwrap_or_else(|_| Err("Thumbnail job panicked".to_string()))
+            .map_err(JobError::Failed);
+
+        let waiters = lock.lock().unwrap().waiters.remove(&key);
+        for waiter in waiters.unwrap_or_default() {
+            let _ = waiter.tx.send(result.clone());
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use crate::extractor::{MetadataExtractor, MetadataFields};
+use crate::i18n::fl;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use serde_json::Value;
+use std::path::Path;
+
+/// Raster images readable by the `image` crate.
+pub struct ImageExtractor;
+
+impl MetadataExtractor for ImageExtractor {
+    fn name(&self) -> &'static str {
+        "image"
+    }
+
+    fn supports(&self, mime_type: &str, extension: &str) -> bool {
+        mime_type.starts_with("image/") && image::ImageFormat::from_extension(extension).is_some()
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let mut decoder = open_decoder(path)?;
+        let (mut width, mut height) = decoder.dimensions();
+        // Report the dimensions as displayed, not as stored
+        if decoder.orientation().is_ok_and(swaps_dimensions) {
+            std::mem::swap(&mut width, &mut height);
+        }
+        let mut fields = MetadataFields::new();
+        fields.insert("width".to_string(), Value::from(width));
+        fields.insert("height".to_string(), Value::from(height));
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        let img = open_oriented(path)?;
+        Ok(Some(img.thumbnail(max_size, max_size)))
+    }
+}
+
+/// Decode an image and rotate/flip it upright according to its EXIF
+/// orientation.
+pub fn open_oriented(path: &Path) -> Result<DynamicImage, String> {
+    let mut decoder = open_decoder(path)?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder)
+        .map_err(|e| fl!("error-decode-image", reason = e.to_string()))?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+/// Whether applying `orientation` turns the image by a quarter turn.
+pub fn swaps_dimensions(orientation: Orientation) -> bool {
+    matches!(
+        orientation,
+        Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Rotate90FlipH
+            | Orientation::Rotate270FlipH
+    )
+}
+
+fn open_decoder(path: &Path) -> Result<impl ImageDecoder, String> {
+    ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| fl!("error-decode-image", reason = e.to_string()))
+}
@@ -0,0 +1,313 @@
+use crate::extractor::{MetadataExtractor, MetadataFields};
+use crate::i18n::fl;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageFormat, ImageReader, RgbImage};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// JPEG start-of-image marker followed by the first segment marker
+const JPEG_SOI: [u8; 3] = [0xFF, 0xD8, 0xFF];
+
+/// Previews beyond this are sensor data or a broken offset
+const MAX_PREVIEW_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Enough of a JPEG to find its dimensions behind the EXIF segment
+const HEADER_BYTES: u64 = 256 * 1024;
+
+/// The TIFF header and the IFDs with camera and date tags sit at the start
+/// of the file, ahead of the image data
+const EXIF_BYTES: u64 = 1024 * 1024;
+
+/// Guards against IFD loops and absurd SubIFD counts in damaged files
+const MAX_IFDS: usize = 32;
+
+/// Fujifilm RAF header; the embedded JPEG's offset and length follow at
+/// `RAF_JPEG_POINTER`, big endian
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW ";
+const RAF_JPEG_POINTER: u64 = 84;
+
+const IFD_ENTRY_LEN: u64 = 12;
+const TYPE_SHORT: u16 = 3;
+const TAG_COMPRESSION: u16 = 0x0103;
+const TAG_STRIP_OFFSETS: u16 = 0x0111;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_STRIP_BYTE_COUNTS: u16 = 0x0117;
+const TAG_SUB_IFDS: u16 = 0x014A;
+const TAG_JPEG_OFFSET: u16 = 0x0201;
+const TAG_JPEG_LENGTH: u16 = 0x0202;
+/// Panasonic's `JpgFromRaw`, stored inline as undefined bytes
+const TAG_RW2_JPEG: u16 = 0x002E;
+const COMPRESSION_JPEG: u64 = 6;
+const COMPRESSION_NEW_JPEG: u64 = 7;
+
+/// Camera RAW photos. Most cameras embed a full-size or near full-size JPEG
+/// preview, which is far cheaper to decode than developing the sensor data;
+/// the RAW itself is only developed when no preview is large enough.
+pub struct RawExtractor;
+
+impl MetadataExtractor for RawExtractor {
+    fn name(&self) -> &'static str {
+        "raw"
+    }
+
+    fn supports(&self, _mime_type: &str, extension: &str) -> bool {
+        matches!(
+            extension,
+            "cr2" | "nef" | "nrw" | "arw" | "dng" | "orf" | "rw2" | "pef" | "raf"
+        )
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let exif = read_exif(path)?;
+        let mut fields = MetadataFields::new();
+        for (tag, key) in [
+            (exif::Tag::Make, "camera_make"),
+            (exif::Tag::Model, "camera_model"),
+            (exif::Tag::DateTimeOriginal, "taken_at"),
+        ] {
+            if let Some(text) = ascii_field(&exif, tag) {
+                fields.insert(key.to_string(), Value::from(text));
+            }
+        }
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        let preview = embedded_preview(path);
+
+        if let Some(preview) = &preview {
+            if preview.width().max(preview.height()) >= max_size {
+                return Ok(Some(preview.thumbnail(max_size, max_size)));
+            }
+        }
+
+        // Developing applies the orientation itself
+        match develop(path, max_size) {
+            Ok(img) => Ok(Some(img)),
+            Err(e) => preview
+                .map(|p| Some(p.thumbnail(max_size, max_size)))
+                .ok_or(e),
+        }
+    }
+}
+
+/// Decode the largest JPEG preview embedded in the file, turned upright.
+/// Only the IFDs and the previews they point at are read, never the whole
+/// RAW.
+fn embedded_preview(path: &Path) -> Option<DynamicImage> {
+    let mut file = File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    let layout = read_layout(&mut file).ok()?;
+
+    let mut candidates: Vec<(u64, JpegRange)> = layout
+        .previews
+        .into_iter()
+        .filter(|r| r.length > 0 && r.length <= MAX_PREVIEW_BYTES)
+        .filter(|r| {
+            r.offset
+                .checked_add(r.length)
+                .is_some_and(|end| end <= file_len)
+        })
+        .filter_map(|range| {
+            let head = read_range(&mut file, range.offset, range.length.min(HEADER_BYTES)).ok()?;
+            if !head.starts_with(&JPEG_SOI) {
+                return None;
+            }
+            let (width, height) = jpeg_reader(&head).into_dimensions().ok()?;
+            Some((width as u64 * height as u64, range))
+        })
+        .collect();
+    candidates.sort_unstable_by_key(|c| std::cmp::Reverse(c.0));
+
+    // Lossless sensor data fails to decode; try the next
+    let mut img = candidates.into_iter().find_map(|(_, range)| {
+        let data = read_range(&mut file, range.offset, range.length).ok()?;
+        jpeg_reader(&data).decode().ok()
+    })?;
+    // Previews are stored as shot; the RAW's own tags say how to turn them
+    if let Some(orientation) = layout.orientation.and_then(Orientation::from_exif) {
+        img.apply_orientation(orientation);
+    }
+    Some(img)
+}
+
+/// Where an embedded JPEG sits in the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct JpegRange {
+    offset: u64,
+    length: u64,
+}
+
+/// What the container says about its previews
+#[derive(Debug, Default)]
+struct RawLayout {
+    previews: Vec<JpegRange>,
+    /// EXIF orientation from IFD0 of the TIFF-based formats
+    orientation: Option<u8>,
+}
+
+/// JPEG streams the container points at, from Fujifilm's header or the IFDs
+/// of the TIFF-based formats (CR2, NEF, ARW, DNG, PEF, RW2, ORF) and their
+/// SubIFDs.
+fn read_layout(file: &mut File) -> io::Result<RawLayout> {
+    let header = read_range(file, 0, RAF_MAGIC.len() as u64)?;
+    if header == RAF_MAGIC {
+        let pointer = read_range(file, RAF_JPEG_POINTER, 8)?;
+        return Ok(RawLayout {
+            previews: vec![JpegRange {
+                offset: u32::from_be_bytes(pointer[0..4].try_into().unwrap()) as u64,
+                length: u32::from_be_bytes(pointer[4..8].try_into().unwrap()) as u64,
+            }],
+            orientation: None,
+        });
+    }
+
+    let little_endian = match &header[0..2] {
+        b"II" => true,
+        b"MM" => false,
+        _ => return Ok(RawLayout::default()),
+    };
+    let u16_at = |b: &[u8]| {
+        let b: [u8; 2] = b[..2].try_into().unwrap();
+        if little_endian {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        }
+    };
+    let u32_at = |b: &[u8]| {
+        let b: [u8; 4] = b[..4].try_into().unwrap();
+        if little_endian {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        }
+    };
+
+    // The magic number after the byte order differs (42, ORF's 0x4F52,
+    // RW2's 0x55), the IFD layout doesn't
+    let mut queue = vec![u32_at(&header[4..8]) as u64];
+    let mut visited: Vec<u64> = Vec::new();
+    let mut layout = RawLayout::default();
+    while let Some(ifd) = queue.pop() {
+        if ifd == 0 || visited.contains(&ifd) || visited.len() >= MAX_IFDS {
+            continue;
+        }
+        let primary = visited.is_empty();
+        visited.push(ifd);
+        let Ok(count) = read_range(file, ifd, 2).map(|b| u16_at(&b) as u64) else {
+            continue;
+        };
+        let Ok(entries) = read_range(file, ifd + 2, count * IFD_ENTRY_LEN + 4) else {
+            continue;
+        };
+
+        let mut jpeg = (None, None);
+        let mut strip = (None, None);
+        let mut compression = None;
+        let (table, next) = entries.split_at((count * IFD_ENTRY_LEN) as usize);
+        for entry in table.chunks_exact(IFD_ENTRY_LEN as usize) {
+            let tag = u16_at(&entry[0..2]);
+            let field_type = u16_at(&entry[2..4]);
+            let values = u32_at(&entry[4..8]) as u64;
+            // A single SHORT sits in the first half of the value field
+            let value = if field_type == TYPE_SHORT {
+                u16_at(&entry[8..10]) as u64
+            } else {
+                u32_at(&entry[8..12]) as u64
+            };
+            match tag {
+                TAG_JPEG_OFFSET => jpeg.0 = Some(value),
+                TAG_JPEG_LENGTH => jpeg.1 = Some(value),
+                TAG_STRIP_OFFSETS if values == 1 => strip.0 = Some(value),
+                TAG_STRIP_BYTE_COUNTS if values == 1 => strip.1 = Some(value),
+                TAG_COMPRESSION => compression = Some(value),
+                TAG_ORIENTATION if primary => layout.orientation = u8::try_from(value).ok(),
+                TAG_RW2_JPEG => layout.previews.push(JpegRange {
+                    offset: value,
+                    length: values,
+                }),
+                TAG_SUB_IFDS if values == 1 => queue.push(value),
+                TAG_SUB_IFDS => {
+                    let Ok(offsets) = read_range(file, value, values.min(MAX_IFDS as u64) * 4)
+                    else {
+                        continue;
+                    };
+                    queue.extend(offsets.chunks_exact(4).map(|o| u32_at(o) as u64));
+                }
+                _ => {}
+            }
+        }
+        if let (Some(offset), Some(length)) = jpeg {
+            layout.previews.push(JpegRange { offset, length });
+        }
+        if let (Some(offset), Some(length), Some(COMPRESSION_JPEG | COMPRESSION_NEW_JPEG)) =
+            (strip.0, strip.1, compression)
+        {
+            layout.previews.push(JpegRange { offset, length });
+        }
+        queue.push(u32_at(next) as u64);
+    }
+    layout.previews.dedup();
+    Ok(layout)
+}
+
+fn read_range(file: &mut File, offset: u64, length: u64) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    file.take(length).read_to_end(&mut data)?;
+    if (data.len() as u64) < length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+fn jpeg_reader(data: &[u8]) -> ImageReader<Cursor<&[u8]>> {
+    ImageReader::with_format(Cursor::new(data), ImageFormat::Jpeg)
+}
+
+/// Demosaic the sensor data, scaled to fit `max_size`.
+fn develop(path: &Path, max_size: u32) -> Result<DynamicImage, String> {
+    let developed = imagepipe::simple_decode_8bit(path, max_size as usize, max_size as usize)
+        .map_err(|e| fl!("error-decode-raw", reason = e.to_string()))?;
+    let img = RgbImage::from_raw(
+        developed.width as u32,
+        developed.height as u32,
+        developed.data,
+    )
+    .ok_or_else(|| fl!("error-invalid-raw-size"))?;
+    Ok(DynamicImage::ImageRgb8(img))
+}
+
+/// Parse the EXIF of the TIFF-based formats from the start of the file.
+/// Tags pointing past `EXIF_BYTES` are skipped rather than reading the
+/// whole RAW for them.
+fn read_exif(path: &Path) -> Result<exif::Exif, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut head = Vec::new();
+    file.take(EXIF_BYTES)
+        .read_to_end(&mut head)
+        .map_err(|e| e.to_string())?;
+    exif::Reader::new()
+        .continue_on_error(true)
+        .read_raw(head)
+        .or_else(|e| e.distill_partial_result(|_| {}))
+        .map_err(|e| fl!("error-read-exif", reason = e.to_string()))
+}
+
+fn ascii_field(exif: &exif::Exif, tag: exif::Tag) -> Option<String> {
+    let field = exif.get_field(tag, exif::In::PRIMARY)?;
+    match &field.value {
+        exif::Value::Ascii(values) => values
+            .first()
+            .map(|v| {
+                String::from_utf8_lossy(v)
+                    .trim_matches(['\0', ' '])
+                    .to_string()
+            })
+            .filter(|s| !s.is_empty()),
+        _ => None,
+    }
+}
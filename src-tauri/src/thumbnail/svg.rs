@@ -0,0 +1,81 @@
+use crate::extractor::{MetadataExtractor, MetadataFields};
+use crate::i18n::fl;
+use image::{DynamicImage, RgbaImage};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{fontdb, Options, Tree};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// SVG (and gzip-compressed SVGZ) drawings, rasterized with resvg.
+pub struct SvgExtractor;
+
+impl MetadataExtractor for SvgExtractor {
+    fn name(&self) -> &'static str {
+        "svg"
+    }
+
+    fn supports(&self, mime_type: &str, extension: &str) -> bool {
+        mime_type == "image/svg+xml" || matches!(extension, "svg" | "svgz")
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let tree = load_tree(path)?;
+        let mut fields = MetadataFields::new();
+        fields.insert("width".to_string(), Value::from(tree.size().width()));
+        fields.insert("height".to_string(), Value::from(tree.size().height()));
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        let tree = load_tree(path)?;
+        let size = tree.size();
+        // Scale up small icons as well, vectors stay sharp at any size
+        let scale = max_size as f32 / size.width().max(size.height());
+        let width = ((size.width() * scale).round() as u32).max(1);
+        let height = ((size.height() * scale).round() as u32).max(1);
+
+        let mut pixmap = Pixmap::new(width, height).ok_or_else(|| fl!("error-invalid-svg-size"))?;
+        resvg::render(
+            &tree,
+            Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+
+        // tiny-skia works in premultiplied alpha
+        let pixels = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|p| {
+                let c = p.demultiply();
+                [c.red(), c.green(), c.blue(), c.alpha()]
+            })
+            .collect();
+        let img =
+            RgbaImage::from_raw(width, height, pixels).ok_or_else(|| fl!("error-render-svg"))?;
+        Ok(Some(DynamicImage::ImageRgba8(img)))
+    }
+}
+
+fn load_tree(path: &Path) -> Result<Tree, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let options = Options {
+        // Relative <image> hrefs resolve next to the SVG file
+        resources_dir: path.parent().map(Path::to_path_buf),
+        fontdb: font_database(),
+        ..Options::default()
+    };
+    Tree::from_data(&data, &options).map_err(|e| fl!("error-parse-svg", reason = e.to_string()))
+}
+
+/// System fonts for `<text>` elements, loaded on first use.
+fn font_database() -> Arc<fontdb::Database> {
+    static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut db = fontdb::Database::new();
+            db.load_system_fonts();
+            Arc::new(db)
+        })
+        .clone()
+}
@@ -0,0 +1,299 @@
+use crate::extractor::{MetadataExtractor, MetadataFields};
+use crate::i18n::fl;
+use image::{DynamicImage, GenericImage};
+use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Positions (as a fraction of the duration) tried for the poster frame
+const POSTER_POSITIONS: [f64; 4] = [0.1, 0.25, 0.5, 0.75];
+
+/// Frames with a mean luma below this (0-255) count as black
+const MIN_MEAN_LUMA: f64 = 24.0;
+
+/// How long ffmpeg or ffprobe may take before it is killed, so a stalled
+/// network share or a pathological file can't hold a worker forever
+const RUN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Common video containers, decoded with a locally installed ffmpeg. Set
+/// `INCHBOX_FFMPEG` to point at a specific binary; `ffprobe` is expected next
+/// to it.
+pub struct VideoExtractor;
+
+impl MetadataExtractor for VideoExtractor {
+    fn name(&self) -> &'static str {
+        "video"
+    }
+
+    fn supports(&self, mime_type: &str, extension: &str) -> bool {
+        // `.ts` is guessed as a transport stream but is mostly TypeScript
+        if extension == "ts" {
+            return false;
+        }
+        mime_type.starts_with("video/")
+            || matches!(
+                extension,
+                "mp4" | "m4v" | "mkv" | "webm" | "mov" | "avi" | "wmv" | "flv" | "mts" | "m2ts"
+            )
+    }
+
+    fn extract(&self, path: &Path) -> Result<MetadataFields, String> {
+        let probe = probe(path)?;
+        let mut fields = MetadataFields::new();
+        if let Some(duration) = duration_secs(&probe) {
+            fields.insert("duration".to_string(), Value::from(duration));
+        }
+        if let Some(stream) = video_stream(&probe) {
+            for key in ["width", "height", "codec_name"] {
+                if let Some(value) = stream.get(key) {
+                    fields.insert(key.to_string(), value.clone());
+                }
+            }
+        }
+        Ok(fields)
+    }
+
+    fn thumbnail(&self, path: &Path, max_size: u32) -> Result<Option<DynamicImage>, String> {
+        let duration = probe(path).ok().and_then(|p| duration_secs(&p));
+
+        // Without a duration (e.g. streams) the first frame is all we can get
+        let Some(duration) = duration else {
+            return extract_frame(path, 0.0, max_size).map(Some);
+        };
+
+        // Skip fade-ins and black intro frames, falling back to the
+        // brightest candidate if every one of them is dark
+        let mut brightest: Option<(f64, DynamicImage)> = None;
+        for position in POSTER_POSITIONS {
+            let Ok(frame) = extract_frame(path, duration * position, max_size) else {
+                continue;
+            };
+            let luma = mean_luma(&frame);
+            if luma >= MIN_MEAN_LUMA {
+                return Ok(Some(frame));
+            }
+            let is_brighter = match &brightest {
+                Some((best, _)) => luma > *best,
+                None => true,
+            };
+            if is_brighter {
+                brightest = Some((luma, frame));
+            }
+        }
+
+        brightest
+            .map(|(_, frame)| Some(frame))
+            .ok_or_else(|| fl!("error-no-video-frame"))
+    }
+}
+
+/// A horizontal sprite of `frame_count` evenly spaced frames, each
+/// `frame_height` pixels tall, for hover scrubbing.
+pub fn generate_filmstrip(
+    path: &Path,
+    frame_count: u32,
+    frame_height: u32,
+) -> Result<DynamicImage, String> {
+    let probe = probe(path)?;
+    let duration = duration_secs(&probe).ok_or_else(|| fl!("error-unknown-video-duration"))?;
+    let frame_count = frame_count.max(1);
+
+    let mut frames = Vec::new();
+    for i in 0..frame_count {
+        // Sample the middle of each segment so the first frame isn't black
+        let position = duration * (i as f64 + 0.5) / frame_count as f64;
+        let frame = extract_frame(path, position, frame_height * 4)?;
+        frames.push(frame.resize(
+            u32::MAX,
+            frame_height,
+            image::imageops::FilterType::Triangle,
+        ));
+    }
+
+    let frame_width = frames.iter().map(|f| f.width()).max().unwrap_or(0);
+    let mut sprite = DynamicImage::new_rgb8(frame_width * frame_count, frame_height);
+    for (i, frame) in frames.iter().enumerate() {
+        let x = i as u32 * frame_width + (frame_width - frame.width()) / 2;
+        sprite
+            .copy_from(frame, x, 0)
+            .map_err(|e| fl!("error-build-filmstrip", reason = e.to_string()))?;
+    }
+
+    Ok(sprite)
+}
+
+fn ffmpeg_binary() -> String {
+    std::env::var("INCHBOX_FFMPEG").unwrap_or_else(|_| "ffmpeg".to_string())
+}
+
+fn ffprobe_binary() -> String {
+    match std::env::var("INCHBOX_FFMPEG") {
+        Ok(ffmpeg) => Path::new(&ffmpeg)
+            .with_file_name(format!("ffprobe{}", std::env::consts::EXE_SUFFIX))
+            .to_string_lossy()
+            .to_string(),
+        Err(_) => "ffprobe".to_string(),
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<Output, String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    }
+    let mut child = command.spawn().map_err(|e| {
+        fl!(
+            "error-run-program",
+            program = program,
+            reason = e.to_string()
+        )
+    })?;
+
+    // Drain both pipes while waiting, or a chatty child blocks on a full pipe
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let status = match wait_timeout(&mut child, RUN_TIMEOUT) {
+        Ok(Some(status)) => status,
+        Ok(None) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(fl!(
+                "error-program-timed-out",
+                program = program,
+                seconds = RUN_TIMEOUT.as_secs()
+            ));
+        }
+        Err(e) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(fl!(
+                "error-run-program",
+                program = program,
+                reason = e.to_string()
+            ));
+        }
+    };
+    let output = Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    };
+    if !output.status.success() {
+        return Err(fl!(
+            "error-program-failed",
+            program = program,
+            reason = String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output)
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+/// Wait for the child to exit, giving up after `timeout`.
+fn wait_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn probe(path: &Path) -> Result<Value, String> {
+    let path = path.to_string_lossy();
+    let output = run(
+        &ffprobe_binary(),
+        &[
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+            &path,
+        ],
+    )?;
+    serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())
+}
+
+fn duration_secs(probe: &Value) -> Option<f64> {
+    probe
+        .get("format")?
+        .get("duration")?
+        .as_str()?
+        .parse::<f64>()
+        .ok()
+        .filter(|d| *d > 0.0)
+}
+
+fn video_stream(probe: &Value) -> Option<&Value> {
+    probe
+        .get("streams")?
+        .as_array()?
+        .iter()
+        .find(|s| s.get("codec_type").and_then(|t| t.as_str()) == Some("video"))
+}
+
+/// Decode the frame at `position` seconds, scaled to fit `max_size`.
+fn extract_frame(path: &Path, position: f64, max_size: u32) -> Result<DynamicImage, String> {
+    let path = path.to_string_lossy();
+    let position = format!("{:.3}", position);
+    let scale = format!(
+        "scale={0}:{0}:force_original_aspect_ratio=decrease",
+        max_size
+    );
+    let output = run(
+        &ffmpeg_binary(),
+        &[
+            "-v",
+            "error",
+            "-ss",
+            &position,
+            "-i",
+            &path,
+            "-frames:v",
+            "1",
+            "-vf",
+            &scale,
+            "-f",
+            "image2pipe",
+            "-vcodec",
+            "png",
+            "-",
+        ],
+    )?;
+    image::load_from_memory(&output.stdout)
+        .map_err(|e| fl!("error-decode-frame", reason = e.to_string()))
+}
+
+fn mean_luma(img: &DynamicImage) -> f64 {
+    let luma = img.to_luma8();
+    let pixels = luma.as_raw();
+    if pixels.is_empty() {
+        return 0.0;
+    }
+    pixels.iter().map(|&p| p as f64).sum::<f64>() / pixels.len() as f64
+}
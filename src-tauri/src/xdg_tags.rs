@@ -0,0 +1,128 @@
+//! Tags in the `user.xdg.tags` extended attribute, as Dolphin/Baloo and
+//! other Linux tools keep them: comma separated, nested tags as paths like
+//! `Travel/Europe`. Elsewhere reading finds nothing and writing does nothing.
+
+use std::io;
+use std::path::Path;
+
+/// Separates tags in the attribute value
+const TAG_SEPARATOR: char = ',';
+
+/// Separates the levels of a nested tag
+const LEVEL_SEPARATOR: char = '/';
+
+/// Tag paths (root first) stored on the file.
+pub fn read(path: &Path) -> io::Result<Vec<Vec<String>>> {
+    Ok(platform::read(path)?
+        .as_deref()
+        .map(parse)
+        .unwrap_or_default())
+}
+
+/// Apply tag changes to the attribute: `removed` tag paths (root first)
+/// go, `added` ones come in, and tags other applications set stay. The
+/// attribute is removed when no tags are left. Returns whether it changed.
+pub fn update(path: &Path, removed: &[Vec<String>], added: &[Vec<String>]) -> io::Result<bool> {
+    let current: Vec<String> = platform::read(path)?
+        .as_deref()
+        .map(parse)
+        .unwrap_or_default()
+        .iter()
+        .map(|levels| format(levels))
+        .collect();
+    let removed: Vec<String> = removed.iter().map(|levels| format(levels)).collect();
+    let mut tags: Vec<String> = current
+        .iter()
+        .filter(|tag| !removed.iter().any(|r| r.eq_ignore_ascii_case(tag)))
+        .cloned()
+        .collect();
+    for tag in added.iter().map(|levels| format(levels)) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+    if tags == current {
+        return Ok(false);
+    }
+    let value = tags.join(&TAG_SEPARATOR.to_string());
+    platform::write(path, (!value.is_empty()).then_some(value.as_str()))?;
+    Ok(true)
+}
+
+fn parse(value: &str) -> Vec<Vec<String>> {
+    value
+        .split(TAG_SEPARATOR)
+        .map(|tag| {
+            tag.split(LEVEL_SEPARATOR)
+                .map(|level| level.trim().to_string())
+                .filter(|level| !level.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .filter(|levels| !levels.is_empty())
+        .collect()
+}
+
+/// One tag as stored. The separators can't be escaped, so they are left
+/// out of names.
+fn format(levels: &[String]) -> String {
+    levels
+        .iter()
+        .map(|level| level.replace([TAG_SEPARATOR, LEVEL_SEPARATOR], " "))
+        .collect::<Vec<_>>()
+        .join(&LEVEL_SEPARATOR.to_string())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::io;
+    use std::path::Path;
+
+    const ATTRIBUTE: &str = "user.xdg.tags";
+
+    /// Filesystems without user attributes (FAT, some network mounts) read
+    /// as having none and ignore writes. They answer ENOTSUP, which std
+    /// doesn't map to `Unsupported`.
+    fn unsupported(e: &io::Error) -> bool {
+        let code = e.raw_os_error();
+        e.kind() == io::ErrorKind::Unsupported
+            || code == Some(libc::ENOTSUP)
+            || code == Some(libc::EOPNOTSUPP)
+    }
+
+    pub fn read(path: &Path) -> io::Result<Option<String>> {
+        match xattr::get(path, ATTRIBUTE) {
+            Ok(value) => Ok(value.map(|bytes| String::from_utf8_lossy(&bytes).to_string())),
+            Err(e) if unsupported(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn write(path: &Path, value: Option<&str>) -> io::Result<()> {
+        let result = match value {
+            Some(value) => xattr::set(path, ATTRIBUTE, value.as_bytes()),
+            None => match xattr::remove(path, ATTRIBUTE) {
+                // It wasn't set
+                Err(e) if e.raw_os_error() == Some(libc::ENODATA) => Ok(()),
+                result => result,
+            },
+        };
+        match result {
+            Err(e) if unsupported(&e) => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use std::io;
+    use std::path::Path;
+
+    pub fn read(_path: &Path) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+
+    pub fn write(_path: &Path, _value: Option<&str>) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,556 @@
+//! Keywords in XMP, as read and written by Lightroom, digiKam and darktable:
+//! flat `dc:subject` and `lr:hierarchicalSubject` paths like
+//! `Travel|Europe|Paris`. Sidecars are read and written; XMP embedded in
+//! images is only read, rewriting the image itself is never worth the risk.
+
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::{Namespace, ResolveResult};
+use quick_xml::{NsReader, Writer};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+const NS_RDF: &[u8] = b"http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const NS_DC: &str = "http://purl.org/dc/elements/1.1/";
+const NS_LR: &str = "http://ns.adobe.com/lightroom/1.0/";
+
+/// Separates the levels of `lr:hierarchicalSubject`
+const HIERARCHY_SEPARATOR: char = '|';
+
+/// Embedded packets sit in the header of the formats we read them from
+const EMBEDDED_SCAN_LIMIT: u64 = 4 * 1024 * 1024;
+
+/// Used when the file has no sidecar yet
+const EMPTY_PACKET: &str = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="InchBox">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""/>
+ </rdf:RDF>
+</x:xmpmeta>
+"#;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Keywords {
+    /// `dc:subject`
+    pub subjects: Vec<String>,
+    /// `lr:hierarchicalSubject`, root first
+    pub hierarchical: Vec<Vec<String>>,
+}
+
+impl Keywords {
+    /// Apply tag changes made in the library: `removed` tag paths (root
+    /// first) go, `added` ones come in, and keywords other applications
+    /// wrote stay. Every level of an added path goes into `dc:subject` too,
+    /// as Lightroom does; a flat keyword only goes with a removed path when
+    /// no remaining path still has it as a level.
+    pub fn apply(&mut self, removed: &[Vec<String>], added: &[Vec<String>]) {
+        self.hierarchical
+            .retain(|path| !removed.iter().any(|r| same_path(path, r)));
+        for name in removed.iter().flatten() {
+            let still_used = self
+                .hierarchical
+                .iter()
+                .chain(added)
+                .flatten()
+                .any(|level| level.eq_ignore_ascii_case(name));
+            if !still_used {
+                self.subjects.retain(|s| !s.eq_ignore_ascii_case(name));
+            }
+        }
+        for path in added {
+            if !self.hierarchical.iter().any(|p| same_path(p, path)) {
+                self.hierarchical.push(path.clone());
+            }
+            for name in path {
+                if !self.subjects.iter().any(|s| s.eq_ignore_ascii_case(name)) {
+                    self.subjects.push(name.clone());
+                }
+            }
+        }
+    }
+
+    /// Tag paths to attach: the hierarchical keywords, plus flat ones that
+    /// aren't a level of any of them.
+    pub fn tag_paths(&self) -> Vec<Vec<String>> {
+        let mut paths = self.hierarchical.clone();
+        for subject in &self.subjects {
+            let covered = self
+                .hierarchical
+                .iter()
+                .flatten()
+                .any(|name| name.eq_ignore_ascii_case(subject));
+            if !covered {
+                paths.push(vec![subject.clone()]);
+            }
+        }
+        paths
+    }
+
+    /// Same keywords, ignoring order.
+    fn same_as(&self, other: &Keywords) -> bool {
+        let sorted = |k: &Keywords| {
+            let mut subjects = k.subjects.clone();
+            let mut hierarchical = k.hierarchical.clone();
+            subjects.sort();
+            hierarchical.sort();
+            (subjects, hierarchical)
+        };
+        sorted(self) == sorted(other)
+    }
+}
+
+/// `photo.jpg.xmp` as darktable and digiKam name it, or Lightroom's
+/// `photo.xmp` when only that one exists and no other file is called
+/// `photo`: next to `photo.cr2` it belongs to the raw as much as to
+/// `photo.jpg`. New sidecars get the former, it can't be shared.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    let mut full = path.as_os_str().to_owned();
+    full.push(".xmp");
+    let full = PathBuf::from(full);
+    let short = path.with_extension("xmp");
+    if !full.exists() && short != path && short.exists() && !has_namesake(path) {
+        short
+    } else {
+        full
+    }
+}
+
+/// Whether another file in the directory has the same name up to the
+/// extension, sidecars aside.
+fn has_namesake(path: &Path) -> bool {
+    let Some(stem) = path.file_stem() else {
+        return false;
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let other = entry.path();
+        other != path
+            && other.file_stem() == Some(stem)
+            && !other
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("xmp"))
+    })
+}
+
+/// Keywords of the file's sidecar, or of the XMP embedded in it for images
+/// without one.
+pub fn read(path: &Path, mime_type: &str) -> io::Result<Keywords> {
+    let sidecar = sidecar_path(path);
+    if sidecar.exists() {
+        return parse(&fs::read_to_string(sidecar)?);
+    }
+    embedded(path, mime_type)
+}
+
+fn embedded(path: &Path, mime_type: &str) -> io::Result<Keywords> {
+    if mime_type.starts_with("image/") {
+        if let Some(packet) = embedded_packet(path)? {
+            return parse(&packet);
+        }
+    }
+    Ok(Keywords::default())
+}
+
+/// Apply tag changes to the file's sidecar (see [`Keywords::apply`]),
+/// keeping everything else in it. A new sidecar starts from the keywords
+/// embedded in the image. Returns whether the sidecar changed; one isn't
+/// created when nothing changes.
+pub fn update(
+    path: &Path,
+    mime_type: &str,
+    removed: &[Vec<String>],
+    added: &[Vec<String>],
+) -> io::Result<bool> {
+    let sidecar = sidecar_path(path);
+    let existing = match fs::read_to_string(&sidecar) {
+        Ok(xml) => Some(xml),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let current = match &existing {
+        Some(xml) => parse(xml)?,
+        None => embedded(path, mime_type)?,
+    };
+    let mut keywords = current.clone();
+    keywords.apply(removed, added);
+    if keywords.same_as(&current) {
+        return Ok(false);
+    }
+    let xml = existing.as_deref().unwrap_or(EMPTY_PACKET);
+    // Through a temporary file, so a failed write can't lose the sidecar
+    let mut temporary = sidecar.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, replace_keywords(xml, &keywords)?)?;
+    fs::rename(&temporary, &sidecar)?;
+    Ok(true)
+}
+
+/// Same levels, ignoring case as tag names do.
+fn same_path(a: &[String], b: &[String]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.eq_ignore_ascii_case(b))
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Property {
+    Subject,
+    HierarchicalSubject,
+}
+
+fn property(ns: &ResolveResult, local_name: &[u8]) -> Option<Property> {
+    match (ns, local_name) {
+        (ResolveResult::Bound(Namespace(ns)), b"subject") if *ns == NS_DC.as_bytes() => {
+            Some(Property::Subject)
+        }
+        (ResolveResult::Bound(Namespace(ns)), b"hierarchicalSubject")
+            if *ns == NS_LR.as_bytes() =>
+        {
+            Some(Property::HierarchicalSubject)
+        }
+        _ => None,
+    }
+}
+
+fn is_rdf(ns: &ResolveResult, local_name: &[u8], expected: &[u8]) -> bool {
+    matches!(ns, ResolveResult::Bound(Namespace(ns)) if *ns == NS_RDF) && local_name == expected
+}
+
+/// Keywords of an XMP packet. Entries of `rdf:Bag` and `rdf:Seq` are read
+/// the same.
+pub fn parse(xml: &str) -> io::Result<Keywords> {
+    let mut reader = NsReader::from_str(xml);
+    let mut keywords = Keywords::default();
+    let mut current: Option<Property> = None;
+    let mut in_item = false;
+    loop {
+        let (ns, event) = reader.read_resolved_event().map_err(invalid_data)?;
+        match event {
+            Event::Start(e) => {
+                if let Some(found) = property(&ns, e.local_name().as_ref()) {
+                    current = Some(found);
+                } else if current.is_some() && is_rdf(&ns, e.local_name().as_ref(), b"li") {
+                    in_item = true;
+                }
+            }
+            Event::Text(t) if in_item => {
+                let text = t.unescape().map_err(invalid_data)?;
+                let text = text.trim();
+                match current {
+                    Some(Property::Subject) if !text.is_empty() => {
+                        keywords.subjects.push(text.to_string())
+                    }
+                    Some(Property::HierarchicalSubject) => {
+                        let path: Vec<String> = text
+                            .split(HIERARCHY_SEPARATOR)
+                            .map(|level| level.trim().to_string())
+                            .filter(|level| !level.is_empty())
+                            .collect();
+                        if !path.is_empty() {
+                            keywords.hierarchical.push(path);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                if in_item {
+                    in_item = false;
+                } else if property(&ns, e.local_name().as_ref()).is_some() {
+                    current = None;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(keywords)
+}
+
+/// `xml` with its `dc:subject` and `lr:hierarchicalSubject` replaced by
+/// `keywords`, added to the first `rdf:Description`.
+fn replace_keywords(xml: &str, keywords: &Keywords) -> io::Result<Vec<u8>> {
+    let mut reader = NsReader::from_str(xml);
+    let mut writer = Writer::new(Vec::new());
+    // Depth inside an old keyword property being dropped
+    let mut skipping = 0usize;
+    let mut inserted = false;
+    loop {
+        let (ns, event) = reader.read_resolved_event().map_err(invalid_data)?;
+        let is_property = match &event {
+            Event::Start(e) | Event::Empty(e) => property(&ns, e.local_name().as_ref()).is_some(),
+            _ => false,
+        };
+        let is_description = match &event {
+            Event::Start(e) | Event::Empty(e) => {
+                is_rdf(&ns, e.local_name().as_ref(), b"Description")
+            }
+            _ => false,
+        };
+
+        if skipping > 0 {
+            match event {
+                Event::Start(_) => skipping += 1,
+                Event::End(_) => skipping -= 1,
+                Event::Eof => break,
+                _ => {}
+            }
+            continue;
+        }
+        match event {
+            Event::Start(_) if is_property => skipping = 1,
+            Event::Empty(_) if is_property => {}
+            Event::Start(e) if is_description && !inserted => {
+                let fragment = keyword_properties(keywords, &rdf_prefix(&e));
+                writer
+                    .write_event(Event::Start(e))
+                    .map_err(io::Error::other)?;
+                writer.get_mut().extend_from_slice(fragment.as_bytes());
+                inserted = true;
+            }
+            Event::Empty(e) if is_description && !inserted => {
+                let fragment = keyword_properties(keywords, &rdf_prefix(&e));
+                writer
+                    .write_event(Event::Start(e.borrow()))
+                    .map_err(io::Error::other)?;
+                writer.get_mut().extend_from_slice(fragment.as_bytes());
+                writer
+                    .write_event(Event::End(e.to_end()))
+                    .map_err(io::Error::other)?;
+                inserted = true;
+            }
+            Event::Eof => break,
+            event => writer.write_event(event).map_err(io::Error::other)?,
+        }
+    }
+    if !inserted {
+        return Err(invalid_data("No rdf:Description in the XMP packet"));
+    }
+    Ok(writer.into_inner())
+}
+
+/// Prefix `rdf:Description` was written with, for the nested `rdf:Bag`.
+fn rdf_prefix(description: &BytesStart) -> String {
+    description
+        .name()
+        .prefix()
+        .map(|p| String::from_utf8_lossy(p.as_ref()).to_string())
+        .unwrap_or_else(|| "rdf".to_string())
+}
+
+/// The keyword properties, declaring their namespaces themselves so they
+/// fit into any `rdf:Description`.
+fn keyword_properties(keywords: &Keywords, rdf: &str) -> String {
+    let bag = |items: Vec<String>| {
+        let items: String = items
+            .iter()
+            .map(|item| format!("<{rdf}:li>{}</{rdf}:li>", escape(item.as_str())))
+            .collect();
+        format!("<{rdf}:Bag>{items}</{rdf}:Bag>")
+    };
+    let mut xml = String::new();
+    if !keywords.subjects.is_empty() {
+        xml.push_str(&format!(
+            "\n   <dc:subject xmlns:dc=\"{NS_DC}\">{}</dc:subject>",
+            bag(keywords.subjects.clone())
+        ));
+    }
+    if !keywords.hierarchical.is_empty() {
+        let paths = keywords
+            .hierarchical
+            .iter()
+            .map(|path| path.join(&HIERARCHY_SEPARATOR.to_string()))
+            .collect();
+        xml.push_str(&format!(
+            "\n   <lr:hierarchicalSubject xmlns:lr=\"{NS_LR}\">{}</lr:hierarchicalSubject>",
+            bag(paths)
+        ));
+    }
+    xml
+}
+
+/// The XMP packet in the first few megabytes of the file, if any.
+fn embedded_packet(path: &Path) -> io::Result<Option<String>> {
+    let mut bytes = Vec::new();
+    File::open(path)?
+        .take(EMBEDDED_SCAN_LIMIT)
+        .read_to_end(&mut bytes)?;
+    let start = find(&bytes, b"<x:xmpmeta");
+    let end = find(&bytes, b"</x:xmpmeta>");
+    Ok(match (start, end) {
+        (Some(start), Some(end)) if start < end => {
+            let packet = &bytes[start..end + b"</x:xmpmeta>".len()];
+            Some(String::from_utf8_lossy(packet).to_string())
+        }
+        _ => None,
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(levels: &[&str]) -> Vec<String> {
+        levels.iter().map(|l| l.to_string()).collect()
+    }
+
+    fn packet(properties: &str) -> String {
+        format!(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description rdf:about="" xmlns:dc="{NS_DC}" xmlns:lr="{NS_LR}" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="3">{properties}</rdf:Description></rdf:RDF></x:xmpmeta>"#
+        )
+    }
+
+    /// A directory of its own under the system temp dir
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("inchbox-xmp-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parses_bag_and_seq() {
+        let xml = packet(
+            "<dc:subject><rdf:Seq><rdf:li>Paris</rdf:li><rdf:li>Cats &amp; Dogs</rdf:li></rdf:Seq></dc:subject>\
+             <lr:hierarchicalSubject><rdf:Bag><rdf:li>Travel|Europe|Paris</rdf:li></rdf:Bag></lr:hierarchicalSubject>",
+        );
+        let keywords = parse(&xml).unwrap();
+        assert_eq!(keywords.subjects, vec!["Paris", "Cats & Dogs"]);
+        assert_eq!(
+            keywords.hierarchical,
+            vec![path(&["Travel", "Europe", "Paris"])]
+        );
+        assert_eq!(
+            keywords.tag_paths(),
+            vec![path(&["Travel", "Europe", "Paris"]), path(&["Cats & Dogs"])]
+        );
+    }
+
+    #[test]
+    fn reads_and_writes_a_foreign_rdf_prefix() {
+        let xml = format!(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><r:RDF xmlns:r="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><r:Description xmlns:d="{NS_DC}"><d:subject><r:Bag><r:li>Paris</r:li></r:Bag></d:subject></r:Description></r:RDF></x:xmpmeta>"#
+        );
+        let mut keywords = parse(&xml).unwrap();
+        assert_eq!(keywords.subjects, vec!["Paris"]);
+
+        keywords.apply(&[], &[path(&["Rome"])]);
+        let written = String::from_utf8(replace_keywords(&xml, &keywords).unwrap()).unwrap();
+        assert!(written.contains("<r:Bag><r:li>Paris</r:li><r:li>Rome</r:li></r:Bag>"));
+        assert_eq!(parse(&written).unwrap(), keywords);
+    }
+
+    #[test]
+    fn fills_an_empty_description() {
+        let mut keywords = Keywords::default();
+        keywords.apply(&[], &[path(&["Travel", "Paris"])]);
+        let written =
+            String::from_utf8(replace_keywords(EMPTY_PACKET, &keywords).unwrap()).unwrap();
+        assert!(written.contains("<rdf:Description rdf:about=\"\">"));
+        assert!(written.contains("</rdf:Description>"));
+        let parsed = parse(&written).unwrap();
+        assert_eq!(parsed.subjects, vec!["Travel", "Paris"]);
+        assert_eq!(parsed.hierarchical, vec![path(&["Travel", "Paris"])]);
+    }
+
+    #[test]
+    fn keeps_other_properties() {
+        let xml = packet(
+            "<dc:subject><rdf:Bag><rdf:li>Paris</rdf:li></rdf:Bag></dc:subject><xmp:Label>Red</xmp:Label>",
+        );
+        let mut keywords = parse(&xml).unwrap();
+        keywords.apply(&[], &[path(&["Rome"])]);
+        let written = String::from_utf8(replace_keywords(&xml, &keywords).unwrap()).unwrap();
+        assert!(written.contains("xmp:Rating=\"3\""));
+        assert!(written.contains("<xmp:Label>Red</xmp:Label>"));
+        assert_eq!(parse(&written).unwrap().subjects, vec!["Paris", "Rome"]);
+    }
+
+    #[test]
+    fn removes_keywords_of_detached_tags_only() {
+        let xml = packet(
+            "<dc:subject><rdf:Bag><rdf:li>Travel</rdf:li><rdf:li>Europe</rdf:li><rdf:li>Paris</rdf:li><rdf:li>Rome</rdf:li><rdf:li>Film</rdf:li></rdf:Bag></dc:subject>\
+             <lr:hierarchicalSubject><rdf:Bag><rdf:li>Travel|Europe|Paris</rdf:li><rdf:li>Travel|Europe|Rome</rdf:li></rdf:Bag></lr:hierarchicalSubject>",
+        );
+        let mut keywords = parse(&xml).unwrap();
+        keywords.apply(&[path(&["travel", "europe", "paris"])], &[]);
+        assert_eq!(
+            keywords.hierarchical,
+            vec![path(&["Travel", "Europe", "Rome"])]
+        );
+        // Levels still used by Rome stay, as does the keyword set elsewhere
+        assert_eq!(keywords.subjects, vec!["Travel", "Europe", "Rome", "Film"]);
+
+        keywords.apply(&[path(&["Travel", "Europe", "Rome"]), path(&["Film"])], &[]);
+        assert_eq!(keywords, Keywords::default());
+        let written = String::from_utf8(replace_keywords(&xml, &keywords).unwrap()).unwrap();
+        assert!(!written.contains("subject"));
+        assert!(written.contains("xmp:Rating=\"3\""));
+    }
+
+    #[test]
+    fn update_only_writes_changes() {
+        let dir = temp_dir("update");
+        let file = dir.join("photo.jpg");
+        fs::write(&file, b"not an image").unwrap();
+        let sidecar = dir.join("photo.jpg.xmp");
+
+        assert!(!update(&file, "image/jpeg", &[], &[]).unwrap());
+        assert!(!sidecar.exists());
+
+        fs::write(
+            &sidecar,
+            packet("<dc:subject><rdf:Bag><rdf:li>Film</rdf:li></rdf:Bag></dc:subject>"),
+        )
+        .unwrap();
+        assert!(update(&file, "image/jpeg", &[], &[path(&["Paris"])]).unwrap());
+        assert!(!update(&file, "image/jpeg", &[], &[path(&["Paris"])]).unwrap());
+        assert_eq!(
+            read(&file, "image/jpeg").unwrap().subjects,
+            vec!["Film", "Paris"]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sidecar_path_prefers_the_full_name() {
+        let dir = temp_dir("full");
+        let file = dir.join("photo.jpg");
+        fs::write(&file, b"").unwrap();
+        assert_eq!(sidecar_path(&file), dir.join("photo.jpg.xmp"));
+
+        fs::write(dir.join("photo.xmp"), b"").unwrap();
+        assert_eq!(sidecar_path(&file), dir.join("photo.xmp"));
+
+        fs::write(dir.join("photo.jpg.xmp"), b"").unwrap();
+        assert_eq!(sidecar_path(&file), dir.join("photo.jpg.xmp"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sidecar_path_leaves_shared_sidecars_alone() {
+        let dir = temp_dir("shared");
+        let jpeg = dir.join("photo.jpg");
+        let raw = dir.join("photo.cr2");
+        fs::write(&jpeg, b"").unwrap();
+        fs::write(&raw, b"").unwrap();
+        fs::write(dir.join("photo.xmp"), b"").unwrap();
+        assert_eq!(sidecar_path(&jpeg), dir.join("photo.jpg.xmp"));
+        assert_eq!(sidecar_path(&raw), dir.join("photo.cr2.xmp"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -13,6 +13,45 @@ export default {
         autostart: {
             label: 'Run on Startup',
             desc: 'Automatically start InchBox when you log in'
+        },
+        xmpSync: {
+            label: 'Sync Tags with XMP',
+            desc: 'Read keywords from XMP when adding files and write tag changes to .xmp sidecars, as Lightroom, digiKam and darktable use them'
+        },
+        xmpSyncNow: {
+            label: 'Sync Library with XMP',
+            desc: 'Attach keywords from XMP to every file and add missing tags to the sidecars',
+            button: 'Sync Now',
+            running: 'Syncing...'
+        },
+        xattrTags: {
+            label: 'Tags in File Attributes',
+            desc: 'Read and write tags in the user.xdg.tags attribute used by Dolphin and other Linux file managers'
+        },
+        pdfium: {
+            label: 'PDF Rendering',
+            available: 'Thumbnails and previews are rendered with pdfium ({library})',
+            unavailable: 'pdfium was not found; PDFs get no thumbnails and open in the built-in viewer. Set INCHBOX_PDFIUM to the library to enable it.'
+        },
+        diagnostics: {
+            label: 'Diagnostics',
+            desc: 'Recent log entries, and a bundle of logs and statistics to attach to bug reports',
+            redactPaths: 'Hide file and folder paths',
+            export: 'Export...',
+            showLogs: 'Show Logs',
+            hideLogs: 'Hide Logs',
+            refresh: 'Refresh',
+            level: 'Level',
+            empty: 'No log entries',
+            loadFailed: 'Failed to read the logs'
+        },
+        libraryArchive: {
+            label: 'Library Backup',
+            desc: 'Export files, tags and groups to an archive, or import one from another machine',
+            includeThumbnails: 'Include thumbnails',
+            export: 'Export...',
+            merge: 'Import...',
+            replace: 'Replace...'
         }
     },
     sidebar: {
@@ -80,9 +119,14 @@ export default {
         editTagsMulti: 'Edit Tags ({n} items)',
         done: 'Done',
         open: 'Open',
+        openWith: 'Open With...',
+        openWithDefaultApp: '{name} (default)',
+        openWithSystemDefault: 'System Default',
         openFileLocation: 'Open File Location',
         copyFile: 'Copy File',
         delete: 'Delete',
+        revealMulti: 'Show {n} items in folder',
+        copyFilesMulti: 'Copy {n} files',
         deleteMulti: 'Delete {n} items',
         clearSelection: 'Clear Selection',
         contextEditTags: 'Edit Tags...',
@@ -91,8 +135,31 @@ export default {
         openFile: 'Open File',
         loading: 'Loading...',
         previewFailed: 'Failed to load preview',
+        pdfPage: 'Page {page} of {count}',
+        previousPage: 'Previous Page',
+        nextPage: 'Next Page',
+        exportList: 'Export List...',
+        exportListMulti: 'Export List of {n} Files...',
+        exportShownList: 'Export List of {n} Shown Files...',
+        exportListTitle: 'Export List of {n} File(s)',
+        exportListFormat: 'Format',
+        exportListColumns: 'Columns',
+        exportListButton: 'Export...',
+        cancel: 'Cancel',
+        columns: {
+            name: 'Name',
+            path: 'Path',
+            extension: 'Extension',
+            size: 'Size',
+            mimeType: 'MIME Type',
+            addedAt: 'Date Added',
+            tags: 'Tags',
+            groups: 'Groups',
+            width: 'Width',
+            height: 'Height',
+            duration: 'Duration'
+        },
         notify: {
-            addedFiles: 'Successfully added {count} file(s)',
             deletedFiles: 'Deleted {count} file(s)',
             groupCreated: 'Group created successfully',
             groupUpdated: 'Group updated',
@@ -108,6 +175,8 @@ export default {
             createGroupFailed: 'Failed to create group',
             updateGroupFailed: 'Failed to update group',
             deleteGroupFailed: 'Failed to delete group',
+            createTagFailed: 'Failed to create tag',
+            renameTagFailed: 'Failed to rename tag',
             moveTagFailed: 'Failed to move tag',
             deleteTagFailed: 'Failed to delete tag',
             attachTagFailed: 'Failed to attach tag',
@@ -115,10 +184,21 @@ export default {
             openFileFailed: 'Failed to open file',
             showInExplorerFailed: 'Failed to show in explorer',
             copyFileFailed: 'Failed to copy file',
-            addFilesResult: 'Added {added} file(s).',
-            skippedDuplicates: 'Skipped {count} duplicate(s).',
-            skippedUnsupported: 'Skipped {count} unsupported file(s).',
-            noFilesAdded: 'No files added.'
+            exportedFileList: 'Exported {count} files',
+            exportFileListFailed: 'Failed to export file list',
+            syncedXmpTags: 'Synced XMP: {files} file(s) got tags, {sidecars} sidecar(s) written',
+            syncXmpTagsFailed: 'Failed to sync tags with XMP',
+            xmpSyncFailedFiles: 'XMP of {count} file(s) could not be read or written.',
+            exportedDiagnostics: 'Saved diagnostics to {path}',
+            exportDiagnosticsFailed: 'Failed to export diagnostics',
+            exportedLibrary: 'Exported {files} file(s) and {thumbnails} thumbnail(s)',
+            exportLibraryFailed: 'Failed to export library',
+            confirmReplaceLibrary: 'Replace the whole library with the archive? Current files, tags and groups will be discarded.',
+            importedLibrary: 'Imported {files} new file(s), merged {merged}, with {thumbnails} thumbnail(s)',
+            importedMissingFiles: '{count} imported file(s) do not exist on this computer.',
+            importLibraryFailed: 'Failed to import library',
+            skippedTagConflicts: 'Left out {count} keyword(s) whose tag name is already used elsewhere.',
+            skippedUnsupported: 'Skipped {count} unsupported file(s).'
         }
     },
     filter: {
@@ -131,6 +211,7 @@ export default {
         ascending: 'Ascending',
         descending: 'Descending',
         fileTypes: 'File Types',
+        color: 'Color',
         all: 'All',
         none: 'None',
         resetDefaults: 'Reset Defaults',
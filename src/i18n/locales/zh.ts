@@ -13,6 +13,45 @@ export default {
         autostart: {
             label: '开机启动',
             desc: '登录时自动启动 InchBox'
+        },
+        xmpSync: {
+            label: '与 XMP 同步标签',
+            desc: '添加文件时读取 XMP 关键词，并将标签变更写入 .xmp 附属文件，与 Lightroom、digiKam 和 darktable 互通'
+        },
+        xmpSyncNow: {
+            label: '将资料库与 XMP 同步',
+            desc: '为所有文件附加 XMP 中的关键词，并将缺少的标签写入附属文件',
+            button: '立即同步',
+            running: '同步中...'
+        },
+        xattrTags: {
+            label: '文件属性中的标签',
+            desc: '读写 Dolphin 等 Linux 文件管理器使用的 user.xdg.tags 属性'
+        },
+        pdfium: {
+            label: 'PDF 渲染',
+            available: '缩略图和预览由 pdfium 渲染（{library}）',
+            unavailable: '未找到 pdfium，PDF 没有缩略图并使用内置查看器打开。将 INCHBOX_PDFIUM 设为库文件路径即可启用。'
+        },
+        diagnostics: {
+            label: '诊断',
+            desc: '查看最近的日志，或导出日志和统计信息以附在问题报告中',
+            redactPaths: '隐藏文件和文件夹路径',
+            export: '导出...',
+            showLogs: '显示日志',
+            hideLogs: '隐藏日志',
+            refresh: '刷新',
+            level: '级别',
+            empty: '没有日志',
+            loadFailed: '读取日志失败'
+        },
+        libraryArchive: {
+            label: '资料库备份',
+            desc: '将文件、标签和分组导出为归档，或导入来自其他电脑的归档',
+            includeThumbnails: '包含缩略图',
+            export: '导出...',
+            merge: '导入...',
+            replace: '替换...'
         }
     },
     sidebar: {
@@ -80,9 +119,14 @@ export default {
         editTagsMulti: '编辑标签 ({n} 个项目)',
         done: '完成',
         open: '打开',
+        openWith: '打开方式...',
+        openWithDefaultApp: '{name}（默认）',
+        openWithSystemDefault: '系统默认',
         openFileLocation: '打开文件所在位置',
         copyFile: '复制文件',
         delete: '删除',
+        revealMulti: '在文件夹中显示 {n} 个项目',
+        copyFilesMulti: '复制 {n} 个文件',
         deleteMulti: '删除 {n} 个项目',
         clearSelection: '清除选择',
         contextEditTags: '编辑标签...',
@@ -91,8 +135,31 @@ export default {
         openFile: '打开文件',
         loading: '加载中...',
         previewFailed: '加载预览失败',
+        pdfPage: '第 {page} 页，共 {count} 页',
+        previousPage: '上一页',
+        nextPage: '下一页',
+        exportList: '导出列表...',
+        exportListMulti: '导出 {n} 个文件的列表...',
+        exportShownList: '导出当前显示的 {n} 个文件的列表...',
+        exportListTitle: '导出 {n} 个文件的列表',
+        exportListFormat: '格式',
+        exportListColumns: '列',
+        exportListButton: '导出...',
+        cancel: '取消',
+        columns: {
+            name: '名称',
+            path: '路径',
+            extension: '扩展名',
+            size: '大小',
+            mimeType: 'MIME 类型',
+            addedAt: '添加日期',
+            tags: '标签',
+            groups: '分组',
+            width: '宽度',
+            height: '高度',
+            duration: '时长'
+        },
         notify: {
-            addedFiles: '成功添加 {count} 个文件',
             deletedFiles: '删除 {count} 个文件',
             groupCreated: '分组创建成功',
             groupUpdated: '分组已更新',
@@ -108,6 +175,8 @@ export default {
             createGroupFailed: '创建分组失败',
             updateGroupFailed: '更新分组失败',
             deleteGroupFailed: '删除分组失败',
+            createTagFailed: '创建标签失败',
+            renameTagFailed: '重命名标签失败',
             moveTagFailed: '移动标签失败',
             deleteTagFailed: '删除标签失败',
             attachTagFailed: '添加标签失败',
@@ -115,10 +184,21 @@ export default {
             openFileFailed: '打开文件失败',
             showInExplorerFailed: '打开所在位置失败',
             copyFileFailed: '复制文件失败',
-            addFilesResult: '添加了 {added} 个文件。',
-            skippedDuplicates: '跳过 {count} 个重复文件。',
-            skippedUnsupported: '跳过 {count} 个不支持的文件。',
-            noFilesAdded: '未添加文件。'
+            exportedFileList: '已导出 {count} 个文件',
+            exportFileListFailed: '导出文件列表失败',
+            syncedXmpTags: 'XMP 同步完成：{files} 个文件获得标签，写入 {sidecars} 个附属文件',
+            syncXmpTagsFailed: '与 XMP 同步标签失败',
+            xmpSyncFailedFiles: '{count} 个文件的 XMP 无法读取或写入。',
+            exportedDiagnostics: '诊断信息已保存到 {path}',
+            exportDiagnosticsFailed: '导出诊断信息失败',
+            exportedLibrary: '已导出 {files} 个文件和 {thumbnails} 个缩略图',
+            exportLibraryFailed: '导出资料库失败',
+            confirmReplaceLibrary: '用归档替换整个资料库？当前的文件、标签和分组将被丢弃。',
+            importedLibrary: '导入了 {files} 个新文件，合并 {merged} 个，包含 {thumbnails} 个缩略图',
+            importedMissingFiles: '{count} 个导入的文件在此电脑上不存在。',
+            importLibraryFailed: '导入资料库失败',
+            skippedTagConflicts: '忽略了 {count} 个关键词，其标签名已在其他位置使用。',
+            skippedUnsupported: '跳过 {count} 个不支持的文件。'
         }
     },
     filter: {
@@ -131,6 +211,7 @@ export default {
         ascending: '升序',
         descending: '降序',
         fileTypes: '文件类型',
+        color: '颜色',
         all: '全部',
         none: '无',
         resetDefaults: '恢复默认',
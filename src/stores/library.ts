@@ -1,10 +1,30 @@
 import {reactive, computed} from 'vue';
 import {invoke} from '@tauri-apps/api/core';
+import {listen, UnlistenFn} from '@tauri-apps/api/event';
+import {ask, open, save} from '@tauri-apps/plugin-dialog';
 import {notify} from './notification';
 import i18n from '../i18n';
 
 const t = (key: string, values?: any) => i18n.global.t(key, values);
 
+export type ListingFormat = 'csv' | 'jsonl';
+
+// Error returned by backend commands: a stable code plus the variant's fields
+export interface InchBoxError {
+    code: string;
+    [field: string]: unknown;
+}
+
+export function isInchBoxError(error: unknown): error is InchBoxError {
+    return typeof error === 'object' && error !== null && typeof (error as InchBoxError).code === 'string';
+}
+
+// Message for a command error, as the backend localized it
+export function describeError(error: unknown, fallbackKey: string): string {
+    if (isInchBoxError(error) && typeof error.message === 'string') return error.message;
+    return t(fallbackKey);
+}
+
 
 export interface Tag {
     id: string;
@@ -19,6 +39,11 @@ export interface TagGroup {
     color: string | null;
 }
 
+export interface Placeholder {
+    blurhash: string;
+    colors: string[]; // '#rrggbb', most prominent first
+}
+
 export interface FileItem {
     id: string;
     name: string;
@@ -28,11 +53,81 @@ export interface FileItem {
     mime_type: string;
     added_at: number;
     tag_ids: string[];
+    metadata: Record<string, unknown>;
+    placeholder: Placeholder | null;
+}
+
+export interface OpenWithApp {
+    id: string;
+    name: string;
+    is_default: boolean;
+    is_preferred: boolean;
+}
+
+export interface PdfiumStatus {
+    available: boolean;
+    // Library that was loaded, or 'system' when found on the search path
+    library: string | null;
+    error: string | null;
+}
+
+export interface PdfPageResponse {
+    path: string;
+    page: number;
+    page_count: number;
+}
+
+export type LogLevel = 'ERROR' | 'WARN' | 'INFO' | 'DEBUG';
+
+export interface LogEntry {
+    timestamp: string;
+    level: string;
+    target: string;
+    // The message plus any structured fields of the event
+    fields: Record<string, unknown>;
 }
 
 interface AddFilesResponse {
     added_files: FileItem[];
     skipped_duplicates: string[];
+    // Tags created for keywords found in the added files
+    created_tags: Tag[];
+    // Keywords left out, their names are used elsewhere among the tags
+    tag_conflicts: TagPathConflict[];
+    // Summary of the above in the current language
+    message: string;
+}
+
+interface TagPathConflict {
+    path: string[];
+    existing: string[];
+}
+
+interface XmpSyncResponse {
+    files_updated: number;
+    sidecars_written: number;
+    created_tags: Tag[];
+    tag_conflicts: TagPathConflict[];
+    failed: string[];
+}
+
+export type ImportMode = 'merge' | 'replace';
+
+interface LibraryManifest {
+    file_count: number;
+    thumbnail_count: number;
+}
+
+interface ImportReport {
+    files_added: number;
+    files_merged: number;
+    files_missing: number;
+    tags_added: number;
+    tags_merged: number;
+    tags_renamed: { from: string, to: string }[];
+    groups_added: number;
+    groups_merged: number;
+    thumbnails_imported: number;
 }
 
 interface AppData {
@@ -56,6 +151,8 @@ interface LibraryState {
         selectedFileIds: string[]; // NEW: track selected files for batch operations
         filters: {
             fileTypes: string[];
+            color: string | null;
+            colorMatchIds: string[]; // Files whose dominant colors match `color`
         };
         tagViewFilters: {
             tags: string[]; // tag IDs
@@ -92,7 +189,9 @@ const initialLibraryState: LibraryState = {
         showFilterPanel: false,
         selectedFileIds: [],
         filters: {
-            fileTypes: []
+            fileTypes: [],
+            color: null,
+            colorMatchIds: []
         },
         tagViewFilters: {
             tags: []
@@ -135,6 +234,31 @@ export function getGroupById(id: string): TagGroup | undefined {
     return libraryStore.groups.find(g => g.id === id);
 }
 
+let unlistenPlaceholders: UnlistenFn | null = null;
+
+// Placeholders are computed as thumbnails get generated
+async function listenForPlaceholders() {
+    if (unlistenPlaceholders) return;
+    unlistenPlaceholders = await listen<{ file_id: string, placeholder: Placeholder }>('file-placeholder', (event) => {
+        const file = libraryStore.files.find(f => f.id === event.payload.file_id);
+        if (file) file.placeholder = event.payload.placeholder;
+    });
+}
+
+let unlistenImports: UnlistenFn | null = null;
+
+// An imported archive changes the whole library, whichever window started it
+async function listenForImports() {
+    if (unlistenImports) return;
+    unlistenImports = await listen<ImportReport>('library-imported', () => {
+        actions.loadData();
+    });
+}
+
+let pdfiumStatus: Promise<PdfiumStatus> | null = null;
+// Whether the backend can thumbnail a type, by mime type and extension
+const thumbnailSupport = new Map<string, Promise<boolean>>();
+
 export const actions = {
     async loadData() {
         libraryStore.isLoading = true;
@@ -143,9 +267,11 @@ export const actions = {
             libraryStore.files = data.files;
             libraryStore.tags = data.tags;
             libraryStore.groups = data.groups;
+            await listenForPlaceholders();
+            await listenForImports();
         } catch (error) {
             console.error('Failed to load data:', error);
-            notify(t('library.notify.loadDataFailed'), 'error');
+            notify(describeError(error, 'library.notify.loadDataFailed'), 'error');
         } finally {
             libraryStore.isLoading = false;
         }
@@ -156,35 +282,40 @@ export const actions = {
             const response = await invoke<AddFilesResponse>('add_files', {paths});
 
             // Update store with added files
+            if (response.created_tags.length > 0) {
+                libraryStore.tags.push(...response.created_tags);
+            }
             if (response.added_files.length > 0) {
                 libraryStore.files.push(...response.added_files);
             }
 
-            // Notifications logic
+            // The backend summarizes the result in the current language
             const addedCount = response.added_files.length;
-            const dupCount = response.skipped_duplicates.length;
-
+            const skipped = response.skipped_duplicates.length + response.tag_conflicts.length;
             if (addedCount > 0) {
-                if (dupCount === 0) {
-                    notify(t('library.notify.addedFiles', {count: addedCount}), 'success');
-                } else {
-                    // Mixed result
-                    let msg = t('library.notify.addFilesResult', {added: addedCount});
-                    if (dupCount > 0) msg += ' ' + t('library.notify.skippedDuplicates', {count: dupCount});
-                    notify(msg, 'warning', 5000);
-                }
-            } else {
-                // No files added
-                if (dupCount > 0) {
-                    let msg = t('library.notify.noFilesAdded');
-                    msg += ' ' + t('library.notify.skippedDuplicates', {count: dupCount});
-                    notify(msg, 'error');
-                }
+                if (skipped === 0) notify(response.message, 'success');
+                else notify(response.message, 'warning', 5000);
+            } else if (skipped > 0) {
+                notify(response.message, 'error');
             }
-
         } catch (error) {
             console.error('Failed to add files:', error);
-            notify(t('library.notify.addFilesFailed'), 'error');
+            notify(describeError(error, 'library.notify.addFilesFailed'), 'error');
+        }
+    },
+
+    async setColorFilter(color: string | null) {
+        libraryStore.ui.filters.color = color;
+        if (!color) {
+            libraryStore.ui.filters.colorMatchIds = [];
+            return;
+        }
+        try {
+            libraryStore.ui.filters.colorMatchIds = await invoke<string[]>('filter_files_by_color', {color});
+        } catch (error) {
+            console.error('Failed to filter by color:', error);
+            libraryStore.ui.filters.color = null;
+            libraryStore.ui.filters.colorMatchIds = [];
         }
     },
 
@@ -196,7 +327,7 @@ export const actions = {
             notify(t('library.notify.deletedFiles', {count: ids.length}), 'success');
         } catch (error) {
             console.error('Failed to delete files:', error);
-            notify(t('library.notify.deleteFilesFailed'), 'error');
+            notify(describeError(error, 'library.notify.deleteFilesFailed'), 'error');
         }
     },
 
@@ -207,7 +338,7 @@ export const actions = {
             notify(t('library.notify.groupCreated'), 'success');
         } catch (error) {
             console.error('Failed to create group:', error);
-            notify(t('library.notify.createGroupFailed'), 'error');
+            notify(describeError(error, 'library.notify.createGroupFailed'), 'error');
         }
     },
 
@@ -222,7 +353,7 @@ export const actions = {
             notify(t('library.notify.groupUpdated'), 'success');
         } catch (error) {
             console.error('Failed to update group:', error);
-            notify(t('library.notify.updateGroupFailed'), 'error');
+            notify(describeError(error, 'library.notify.updateGroupFailed'), 'error');
         }
     },
 
@@ -244,7 +375,7 @@ export const actions = {
             notify(t('library.notify.groupDeleted'), 'success');
         } catch (error) {
             console.error('Failed to delete group:', error);
-            notify(t('library.notify.deleteGroupFailed'), 'error');
+            notify(describeError(error, 'library.notify.deleteGroupFailed'), 'error');
         }
     },
 
@@ -256,8 +387,7 @@ export const actions = {
             return tag;
         } catch (error) {
             console.error('Failed to create tag:', error);
-            // Backend returns string error message for duplicates
-            notify(String(error), 'error');
+            notify(describeError(error, 'library.notify.createTagFailed'), 'error');
             return null;
         }
     },
@@ -270,7 +400,7 @@ export const actions = {
             notify(t('library.notify.tagRenamed'), 'success');
         } catch (error) {
             console.error('Failed to rename tag:', error);
-            notify(String(error), 'error');
+            notify(describeError(error, 'library.notify.renameTagFailed'), 'error');
         }
     },
 
@@ -295,7 +425,7 @@ export const actions = {
             notify(t('library.notify.tagMoved'), 'success');
         } catch (error) {
             console.error('Failed to move tag:', error);
-            notify(t('library.notify.moveTagFailed'), 'error');
+            notify(describeError(error, 'library.notify.moveTagFailed'), 'error');
         }
     },
 
@@ -319,7 +449,7 @@ export const actions = {
             notify(t('library.notify.tagDeleted'), 'success');
         } catch (error) {
             console.error('Failed to delete tag:', error);
-            notify(t('library.notify.deleteTagFailed'), 'error');
+            notify(describeError(error, 'library.notify.deleteTagFailed'), 'error');
         }
     },
 
@@ -357,7 +487,7 @@ export const actions = {
             }
         } catch (error) {
             console.error('Failed to attach tag:', error);
-            notify(t('library.notify.attachTagFailed'), 'error');
+            notify(describeError(error, 'library.notify.attachTagFailed'), 'error');
         }
     },
 
@@ -370,7 +500,7 @@ export const actions = {
             }
         } catch (error) {
             console.error('Failed to detach tag:', error);
-            notify(t('library.notify.detachTagFailed'), 'error');
+            notify(describeError(error, 'library.notify.detachTagFailed'), 'error');
         }
     },
 
@@ -379,26 +509,181 @@ export const actions = {
             await invoke('open_file_default', {path});
         } catch (error) {
             console.error('Failed to open file:', error);
-            notify(t('library.notify.openFileFailed'), 'error');
+            notify(describeError(error, 'library.notify.openFileFailed'), 'error');
         }
     },
 
-    async showInExplorer(path: string) {
+    async getOpenWithApps(path: string): Promise<OpenWithApp[]> {
         try {
-            await invoke('show_in_explorer', {path});
+            return await invoke<OpenWithApp[]>('get_open_with_apps', {path});
+        } catch (error) {
+            console.error('Failed to list applications:', error);
+            return [];
+        }
+    },
+
+    // An empty appId opens with (and, remembered, goes back to) the system default
+    async openFileWith(path: string, appId: string, remember = true) {
+        try {
+            await invoke('open_file_with', {path, appId, remember});
+        } catch (error) {
+            console.error('Failed to open file with application:', error);
+            notify(describeError(error, 'library.notify.openFileFailed'), 'error');
+        }
+    },
+
+    async showInExplorer(paths: string[]) {
+        if (paths.length === 0) return;
+        try {
+            await invoke('show_in_explorer', {paths});
         } catch (error) {
             console.error('Failed to show in explorer:', error);
-            notify(t('library.notify.showInExplorerFailed'), 'error');
+            notify(describeError(error, 'library.notify.showInExplorerFailed'), 'error');
         }
     },
 
-    async copyFile(path: string) {
+    async copyFiles(paths: string[]) {
+        if (paths.length === 0) return;
         try {
-            await invoke('copy_file_to_clipboard', {path});
+            await invoke('copy_files_to_clipboard', {paths});
             notify(t('library.notify.copiedToClipboard'), 'success');
         } catch (error) {
             console.error('Failed to copy file:', error);
-            notify(t('library.notify.copyFileFailed'), 'error');
+            notify(describeError(error, 'library.notify.copyFileFailed'), 'error');
         }
+    },
+
+    // Keywords in XMP become tags and tags go into the sidecars, for the
+    // whole library
+    async syncXmpTags() {
+        try {
+            const response = await invoke<XmpSyncResponse>('sync_xmp_tags', {fileIds: null});
+            if (response.files_updated > 0 || response.created_tags.length > 0) {
+                await this.loadData();
+            }
+            notify(t('library.notify.syncedXmpTags', {
+                files: response.files_updated,
+                sidecars: response.sidecars_written
+            }), 'success');
+            if (response.tag_conflicts.length > 0) {
+                notify(t('library.notify.skippedTagConflicts', {count: response.tag_conflicts.length}), 'warning', 5000);
+            }
+            if (response.failed.length > 0) {
+                notify(t('library.notify.xmpSyncFailedFiles', {count: response.failed.length}), 'warning', 5000);
+            }
+        } catch (error) {
+            console.error('Failed to sync XMP tags:', error);
+            notify(describeError(error, 'library.notify.syncXmpTagsFailed'), 'error');
+        }
+    },
+
+    async exportLibrary(includeThumbnails: boolean) {
+        const destination = await save({
+            defaultPath: 'inchbox-library.zip',
+            filters: [{name: 'InchBox Library', extensions: ['zip']}]
+        });
+        if (!destination) return;
+        try {
+            const manifest = await invoke<LibraryManifest>('export_library', {destination, includeThumbnails});
+            notify(t('library.notify.exportedLibrary', {
+                files: manifest.file_count,
+                thumbnails: manifest.thumbnail_count
+            }), 'success');
+        } catch (error) {
+            console.error('Failed to export library:', error);
+            notify(describeError(error, 'library.notify.exportLibraryFailed'), 'error');
+        }
+    },
+
+    // The library reloads on the `library-imported` event
+    async importLibrary(mode: ImportMode) {
+        const source = await open({
+            multiple: false,
+            filters: [{name: 'InchBox Library', extensions: ['zip']}]
+        });
+        if (typeof source !== 'string') return;
+        if (mode === 'replace') {
+            const confirmed = await ask(t('library.notify.confirmReplaceLibrary'), {kind: 'warning'});
+            if (!confirmed) return;
+        }
+        try {
+            const report = await invoke<ImportReport>('import_library', {source, mode});
+            notify(t('library.notify.importedLibrary', {
+                files: report.files_added,
+                merged: report.files_merged,
+                thumbnails: report.thumbnails_imported
+            }), 'success');
+            if (report.files_missing > 0) {
+                notify(t('library.notify.importedMissingFiles', {count: report.files_missing}), 'warning', 5000);
+            }
+        } catch (error) {
+            console.error('Failed to import library:', error);
+            notify(describeError(error, 'library.notify.importLibraryFailed'), 'error');
+        }
+    },
+
+    // Columns: name, path, extension, size, mime_type, added_at, tags, groups
+    // and metadata.<field>
+    async exportFileList(fileIds: string[] | null, format: ListingFormat, columns: string[]) {
+        const destination = await save({
+            defaultPath: `inchbox-files.${format}`,
+            filters: [format === 'csv'
+                ? {name: 'CSV', extensions: ['csv']}
+                : {name: 'JSON Lines', extensions: ['jsonl']}]
+        });
+        if (!destination) return;
+        try {
+            const count = await invoke<number>('export_file_list', {fileIds, destination, format, columns});
+            notify(t('library.notify.exportedFileList', {count}), 'success');
+        } catch (error) {
+            console.error('Failed to export file list:', error);
+            notify(describeError(error, 'library.notify.exportFileListFailed'), 'error');
+        }
+    },
+
+    // Newest entries last; errors are left to the caller
+    async getRecentLogs(limit: number, minLevel: LogLevel): Promise<LogEntry[]> {
+        return invoke<LogEntry[]>('get_recent_logs', {limit, minLevel});
+    },
+
+    async exportDiagnostics(redactPaths: boolean) {
+        const destination = await save({
+            defaultPath: 'inchbox-diagnostics.zip',
+            filters: [{name: 'ZIP', extensions: ['zip']}]
+        });
+        if (!destination) return;
+        try {
+            const path = await invoke<string>('export_diagnostics', {destination, redactPaths});
+            notify(t('library.notify.exportedDiagnostics', {path}), 'success');
+        } catch (error) {
+            console.error('Failed to export diagnostics:', error);
+            notify(describeError(error, 'library.notify.exportDiagnosticsFailed'), 'error');
+        }
+    },
+
+    // Asked once; pdfium doesn't come and go while the app runs
+    getPdfiumStatus(): Promise<PdfiumStatus> {
+        pdfiumStatus ??= invoke<PdfiumStatus>('get_pdfium_status').catch(error => {
+            console.error('Failed to get pdfium status:', error);
+            return {available: false, library: null, error: String(error)};
+        });
+        return pdfiumStatus;
+    },
+
+    // Asked once per type and shared by every card showing one
+    supportsThumbnail(file: FileItem): Promise<boolean> {
+        const key = `${file.mime_type}|${file.extension.toLowerCase()}`;
+        let supported = thumbnailSupport.get(key);
+        if (!supported) {
+            supported = invoke<boolean>('supports_thumbnail', {mimeType: file.mime_type, extension: file.extension})
+                .catch(() => false);
+            thumbnailSupport.set(key, supported);
+        }
+        return supported;
+    },
+
+    // One page of a PDF at preview resolution; errors are left to the caller
+    async renderPdfPage(fileId: string, page: number): Promise<PdfPageResponse> {
+        return invoke<PdfPageResponse>('render_pdf_page', {fileId, page});
     }
 };